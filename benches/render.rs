@@ -0,0 +1,67 @@
+//! compares drawing the grid with an occupancy map against searching the worms for every cell.
+//! run with `cargo bench`
+
+use std::{
+	fs,
+	hint::black_box,
+	io::{self, Write},
+	time::{Duration, Instant},
+};
+
+use owo_colors::OwoColorize;
+use worm::{draw_grid, Occupancy, SandWormInterpreter, Viewport};
+
+const FRAMES: u32 = 200;
+
+/// the old way of drawing, looking up each cell in every worm body
+fn draw_grid_per_cell(interpreter: &SandWormInterpreter, out: &mut impl Write) -> io::Result<()> {
+	for (row, line) in interpreter.program().iter().enumerate() {
+		for (col, &byte) in line.iter().enumerate() {
+			let in_body = interpreter
+				.worms()
+				.iter()
+				.any(|w| w.is_alive() && w.body().contains(&(col, row)));
+			if in_body {
+				write!(out, "{:x}", byte.on_green())?;
+			} else {
+				write!(out, "{}", byte as char)?;
+			}
+		}
+		writeln!(out)?;
+	}
+	Ok(())
+}
+
+fn time(mut frame: impl FnMut()) -> Duration {
+	let start = Instant::now();
+	for _ in 0..FRAMES {
+		frame();
+	}
+	start.elapsed() / FRAMES
+}
+
+fn main() {
+	let source = fs::read_to_string("programs/99_bottles_of_beer.worm").unwrap();
+	for steps in [0, 2_000, 20_000] {
+		let mut interpreter = SandWormInterpreter::new(&source, Vec::new()).unwrap();
+		interpreter.step(steps).unwrap();
+		let len = interpreter.worm().body().len();
+		let mut out = io::sink();
+
+		let per_cell = time(|| draw_grid_per_cell(black_box(&interpreter), &mut out).unwrap());
+		let mut occupancy = Occupancy::default();
+		let view = Viewport {
+			x: 0,
+			y: 0,
+			width: interpreter.width(),
+			height: interpreter.height(),
+		};
+		let with_map = time(|| {
+			occupancy.update(black_box(&interpreter));
+			draw_grid(&interpreter, &occupancy, view, &mut out).unwrap();
+		});
+		println!(
+			"99 bottles after {steps} steps, worm length {len}: per cell {per_cell:?}, occupancy map {with_map:?}"
+		);
+	}
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+use crate::{Expr, SandWormInterpreter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+	/// a worm head is on this cell, counted from the top left of the source
+	Position(i64, i64),
+	/// the current worm is about to execute this instruction
+	Instruction(u8),
+	/// the expression is true, evaluation errors count as false
+	Condition(Expr),
+}
+
+impl Breakpoint {
+	pub fn is_hit(&self, interpreter: &SandWormInterpreter) -> bool {
+		match self {
+			Breakpoint::Position(x, y) => interpreter.worms().iter().any(|worm| {
+				worm.is_alive() && interpreter.source_position(worm.head()) == (*x, *y)
+			}),
+			Breakpoint::Instruction(byte) => interpreter.next_instruction() == Some(*byte),
+			Breakpoint::Condition(expr) => expr.is_true(interpreter).unwrap_or(false),
+		}
+	}
+}
+
+impl fmt::Display for Breakpoint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Breakpoint::Position(x, y) => write!(f, "{x} {y}"),
+			Breakpoint::Instruction(byte) => write!(f, "char {:?}", *byte as char),
+			Breakpoint::Condition(expr) => write!(f, "if {expr}"),
+		}
+	}
+}
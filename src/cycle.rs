@@ -0,0 +1,147 @@
+use std::{
+	collections::HashMap,
+	fmt,
+	hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::{lock_input, SandWormInterpreter};
+
+/// how to look for repeating states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleMode {
+	/// remember every state, and report exactly where the cycle began.
+	/// uses memory for every step
+	Exact,
+	/// Brent's algorithm, which only remembers one state. it may take up to
+	/// twice as long to notice a cycle, and only knows roughly where it began
+	Heuristic,
+}
+
+/// a state that came back, so the program will repeat the same steps forever
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+	/// steps from one repeat to the next
+	pub length: usize,
+	/// the step the cycle began at. in heuristic mode it began at this step or earlier
+	pub start: usize,
+	pub mode: CycleMode,
+}
+
+/// watches the interpreter for states it has been in before.
+/// call `check` before the first step and after every step
+#[derive(Debug, Clone)]
+pub struct CycleDetector {
+	mode: CycleMode,
+	/// exact mode: the first step every state was seen at
+	seen: HashMap<u64, usize>,
+	/// heuristic mode: the state being compared against, and its step
+	saved: Option<(u64, usize)>,
+	/// heuristic mode: checks until `saved` is replaced
+	power: usize,
+	checks: usize,
+}
+
+impl CycleDetector {
+	pub fn new(mode: CycleMode) -> Self {
+		Self {
+			mode,
+			seen: HashMap::new(),
+			saved: None,
+			power: 1,
+			checks: 0,
+		}
+	}
+
+	pub fn mode(&self) -> CycleMode {
+		self.mode
+	}
+
+	/// forget everything seen so far, for when the interpreter jumps to another state
+	pub fn reset(&mut self) {
+		*self = Self::new(self.mode);
+	}
+
+	pub fn check(&mut self, interpreter: &SandWormInterpreter) -> Option<Cycle> {
+		let hash = interpreter.state_hash();
+		let step = interpreter.steps();
+		match self.mode {
+			CycleMode::Exact => {
+				let start = *self.seen.entry(hash).or_insert(step);
+				(start != step).then_some(Cycle {
+					length: step - start,
+					start,
+					mode: self.mode,
+				})
+			}
+			CycleMode::Heuristic => {
+				if let Some((saved, saved_step)) = self.saved {
+					if saved == hash && saved_step != step {
+						return Some(Cycle {
+							length: step - saved_step,
+							start: saved_step,
+							mode: self.mode,
+						});
+					}
+				}
+				self.checks += 1;
+				if self.saved.is_none() || self.checks == self.power {
+					self.saved = Some((hash, step));
+					self.power *= 2;
+					self.checks = 0;
+				}
+				None
+			}
+		}
+	}
+}
+
+impl SandWormInterpreter {
+	/// a hash of everything that decides what the program does next.
+	/// the output and step count are left out, so a program that loops while printing still repeats
+	pub fn state_hash(&self) -> u64 {
+		let mut hasher = DefaultHasher::new();
+		self.width.hash(&mut hasher);
+		self.height.hash(&mut hasher);
+		// the grid under a living body only holds the `@` its head left there,
+		// the values are hashed with the worms below
+		self.program.hash(&mut hasher);
+		self.current.hash(&mut hasher);
+		self.blocked_turns.hash(&mut hasher);
+		self.topology.hash(&mut hasher);
+		self.state.hash(&mut hasher);
+		// once the input has run out, reading any further gives the same zeros
+		let input = lock_input(&self.input);
+		if input.is_exhausted(self.input_index) {
+			usize::MAX.hash(&mut hasher);
+		} else {
+			self.input_index.hash(&mut hasher);
+		}
+		for worm in &self.worms {
+			worm.alive.hash(&mut hasher);
+			worm.head.hash(&mut hasher);
+			worm.direction.hash(&mut hasher);
+			// a worm that has left only has its old body, its values are on the grid now
+			if worm.alive {
+				worm.body().hash(&mut hasher);
+				worm.values.hash(&mut hasher);
+			}
+			worm.worm_in.hash(&mut hasher);
+			worm.worm_out.hash(&mut hasher);
+		}
+		hasher.finish()
+	}
+}
+
+impl fmt::Display for Cycle {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"infinite loop: the program repeats every {} steps, starting at step {}",
+			self.length, self.start
+		)?;
+		if self.mode == CycleMode::Heuristic {
+			write!(f, " or earlier")?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,53 @@
+use std::{fmt, io};
+
+use crate::{Diagnostic, Severity};
+
+#[derive(Debug)]
+pub enum WormError {
+	/// the source is not a valid program. includes any warnings too
+	Parse(Vec<Diagnostic>),
+	/// reading input failed
+	Io(io::Error),
+	/// the interpreter got into a state that breaks its own rules
+	Corrupted(String),
+	/// the program needs more memory than it is allowed
+	ResourceLimit(String),
+	/// a saved snapshot could not be read
+	Snapshot { line: usize, message: String },
+}
+
+impl fmt::Display for WormError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			WormError::Parse(diagnostics) => {
+				let errors: Vec<_> = diagnostics
+					.iter()
+					.filter(|d| d.severity == Severity::Error)
+					.map(|d| format!("{} at {}:{}", d.message, d.line, d.column))
+					.collect();
+				write!(f, "parse error: {}", errors.join(", "))
+			}
+			WormError::Io(err) => write!(f, "io error: {err}"),
+			WormError::Corrupted(msg) => write!(f, "interpreter state corrupted: {msg}"),
+			WormError::ResourceLimit(msg) => write!(f, "resource limit reached: {msg}"),
+			WormError::Snapshot { line, message } => {
+				write!(f, "invalid snapshot at line {line}: {message}")
+			}
+		}
+	}
+}
+
+impl std::error::Error for WormError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			WormError::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for WormError {
+	fn from(err: io::Error) -> Self {
+		WormError::Io(err)
+	}
+}
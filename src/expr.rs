@@ -0,0 +1,425 @@
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use crate::{Direction, SandWormInterpreter};
+
+/// a small expression over the interpreter state, like `len > 5 && top == '!'`
+/// or `output contains "beer"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+	source: String,
+	root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+	Int(i64),
+	Str(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError(String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+	Literal(Value),
+	Var(Var),
+	Not(Box<Node>),
+	Binary(Box<Node>, Op, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+	Steps,
+	Len,
+	Top,
+	X,
+	Y,
+	Dir,
+	Next,
+	Output,
+	Input,
+	InputIndex,
+	Worm,
+	Worms,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+	Or,
+	And,
+	Eq,
+	Ne,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Int(i64),
+	Str(Vec<u8>),
+	Ident(String),
+	Op(Op),
+	Not,
+	Open,
+	Close,
+}
+
+impl Expr {
+	pub fn parse(source: &str) -> Result<Self, ExprError> {
+		let tokens = tokenize(source)?;
+		let mut parser = Parser { tokens, pos: 0 };
+		let root = parser.or()?;
+		if let Some(token) = parser.tokens.get(parser.pos) {
+			return Err(ExprError(format!("unexpected {token:?}")));
+		}
+		Ok(Self {
+			source: source.trim().to_owned(),
+			root,
+		})
+	}
+
+	pub fn eval(&self, interpreter: &SandWormInterpreter) -> Result<Value, ExprError> {
+		let input = interpreter.input();
+		let context = Context {
+			interpreter,
+			input: &input,
+		};
+		self.root.eval(&context).map(Value::from)
+	}
+
+	/// evaluates the expression as a condition, where nonzero numbers and
+	/// non-empty strings are true
+	pub fn is_true(&self, interpreter: &SandWormInterpreter) -> Result<bool, ExprError> {
+		let input = interpreter.input();
+		let context = Context {
+			interpreter,
+			input: &input,
+		};
+		self.root.eval(&context).map(|value| value.is_true())
+	}
+}
+
+/// what an expression can see, with the input locked once for the whole evaluation
+struct Context<'a> {
+	interpreter: &'a SandWormInterpreter,
+	input: &'a [u8],
+}
+
+/// a value borrowed from the expression or the interpreter while evaluating,
+/// so checking the output on every step doesn't copy it every time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Val<'a> {
+	Int(i64),
+	Str(&'a [u8]),
+}
+
+impl Val<'_> {
+	fn is_true(self) -> bool {
+		match self {
+			Val::Int(n) => n != 0,
+			Val::Str(s) => !s.is_empty(),
+		}
+	}
+}
+
+impl From<Val<'_>> for Value {
+	fn from(value: Val<'_>) -> Self {
+		match value {
+			Val::Int(n) => Value::Int(n),
+			Val::Str(s) => Value::Str(s.to_vec()),
+		}
+	}
+}
+
+impl<'a> From<&'a Value> for Val<'a> {
+	fn from(value: &'a Value) -> Self {
+		match value {
+			Value::Int(n) => Val::Int(*n),
+			Value::Str(s) => Val::Str(s),
+		}
+	}
+}
+
+impl Value {
+	pub fn is_true(&self) -> bool {
+		match self {
+			Value::Int(n) => *n != 0,
+			Value::Str(s) => !s.is_empty(),
+		}
+	}
+}
+
+impl Node {
+	fn eval<'a>(&'a self, context: &Context<'a>) -> Result<Val<'a>, ExprError> {
+		Ok(match self {
+			Node::Literal(value) => Val::from(value),
+			Node::Var(var) => var.eval(context),
+			Node::Not(inner) => Val::Int(!inner.eval(context)?.is_true() as i64),
+			Node::Binary(left, Op::And, right) => {
+				Val::Int((left.eval(context)?.is_true() && right.eval(context)?.is_true()) as i64)
+			}
+			Node::Binary(left, Op::Or, right) => {
+				Val::Int((left.eval(context)?.is_true() || right.eval(context)?.is_true()) as i64)
+			}
+			Node::Binary(left, op, right) => {
+				let left = left.eval(context)?;
+				let right = right.eval(context)?;
+				let result = match (op, left, right) {
+					(Op::Eq, _, _) => left == right,
+					(Op::Ne, _, _) => left != right,
+					(Op::Contains, Val::Str(haystack), Val::Str(needle)) => {
+						needle.is_empty() || haystack.windows(needle.len()).any(|w| w == needle)
+					}
+					(Op::Contains, Val::Str(haystack), Val::Int(byte)) => {
+						haystack.iter().any(|&b| b as i64 == byte)
+					}
+					(_, Val::Int(a), Val::Int(b)) => match op {
+						Op::Lt => a < b,
+						Op::Le => a <= b,
+						Op::Gt => a > b,
+						Op::Ge => a >= b,
+						_ => return Err(ExprError(format!("cannot use {op:?} on numbers"))),
+					},
+					(_, Val::Str(a), Val::Str(b)) => match op {
+						Op::Lt => a < b,
+						Op::Le => a <= b,
+						Op::Gt => a > b,
+						Op::Ge => a >= b,
+						_ => return Err(ExprError(format!("cannot use {op:?} on strings"))),
+					},
+					_ => {
+						return Err(ExprError(format!(
+							"cannot compare {} and {} with {op:?}",
+							Value::from(left),
+							Value::from(right)
+						)))
+					}
+				};
+				Val::Int(result as i64)
+			}
+		})
+	}
+}
+
+impl Var {
+	fn from_name(name: &str) -> Option<Self> {
+		Some(match name {
+			"steps" => Var::Steps,
+			"len" => Var::Len,
+			"top" => Var::Top,
+			"x" => Var::X,
+			"y" => Var::Y,
+			"dir" => Var::Dir,
+			"next" => Var::Next,
+			"output" => Var::Output,
+			"input" => Var::Input,
+			"input_index" => Var::InputIndex,
+			"worm" => Var::Worm,
+			"worms" => Var::Worms,
+			_ => return None,
+		})
+	}
+
+	fn eval<'a>(self, context: &Context<'a>) -> Val<'a> {
+		let interpreter = context.interpreter;
+		let worm = interpreter.worm();
+		match self {
+			Var::Steps => Val::Int(interpreter.steps() as i64),
+			Var::Len => Val::Int(worm.body().len() as i64),
+			Var::Top => Val::Int(worm.values().last().copied().unwrap_or_default() as i64),
+			Var::X => Val::Int(interpreter.source_position(worm.head()).0),
+			Var::Y => Val::Int(interpreter.source_position(worm.head()).1),
+			Var::Dir => Val::Str(match worm.direction() {
+				Direction::Up => b"up",
+				Direction::Down => b"down",
+				Direction::Left => b"left",
+				Direction::Right => b"right",
+			}),
+			Var::Next => Val::Int(interpreter.next_instruction().map_or(-1, |b| b as i64)),
+			Var::Output => Val::Str(interpreter.output()),
+			Var::Input => Val::Str(context.input),
+			Var::InputIndex => Val::Int(interpreter.input_index() as i64),
+			Var::Worm => Val::Int(interpreter.current() as i64),
+			Var::Worms => {
+				Val::Int(interpreter.worms().iter().filter(|w| w.is_alive()).count() as i64)
+			}
+		}
+	}
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	pos: usize,
+}
+
+impl Parser {
+	fn peek_op(&self) -> Option<Op> {
+		match self.tokens.get(self.pos) {
+			Some(Token::Op(op)) => Some(*op),
+			_ => None,
+		}
+	}
+
+	fn or(&mut self) -> Result<Node, ExprError> {
+		let mut node = self.and()?;
+		while self.peek_op() == Some(Op::Or) {
+			self.pos += 1;
+			node = Node::Binary(Box::new(node), Op::Or, Box::new(self.and()?));
+		}
+		Ok(node)
+	}
+
+	fn and(&mut self) -> Result<Node, ExprError> {
+		let mut node = self.comparison()?;
+		while self.peek_op() == Some(Op::And) {
+			self.pos += 1;
+			node = Node::Binary(Box::new(node), Op::And, Box::new(self.comparison()?));
+		}
+		Ok(node)
+	}
+
+	fn comparison(&mut self) -> Result<Node, ExprError> {
+		let node = self.unary()?;
+		match self.peek_op() {
+			Some(op) if !matches!(op, Op::And | Op::Or) => {
+				self.pos += 1;
+				Ok(Node::Binary(Box::new(node), op, Box::new(self.unary()?)))
+			}
+			_ => Ok(node),
+		}
+	}
+
+	fn unary(&mut self) -> Result<Node, ExprError> {
+		let token = self.tokens.get(self.pos).cloned();
+		self.pos += 1;
+		match token {
+			Some(Token::Int(n)) => Ok(Node::Literal(Value::Int(n))),
+			Some(Token::Str(s)) => Ok(Node::Literal(Value::Str(s))),
+			Some(Token::Ident(name)) => Var::from_name(&name)
+				.map(Node::Var)
+				.ok_or_else(|| ExprError(format!("unknown variable '{name}'"))),
+			Some(Token::Not) => Ok(Node::Not(Box::new(self.unary()?))),
+			Some(Token::Open) => {
+				let node = self.or()?;
+				if self.tokens.get(self.pos) != Some(&Token::Close) {
+					return Err(ExprError("missing ')'".into()));
+				}
+				self.pos += 1;
+				Ok(node)
+			}
+			Some(token) => Err(ExprError(format!("unexpected {token:?}"))),
+			None => Err(ExprError("unexpected end of expression".into())),
+		}
+	}
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExprError> {
+	let mut tokens = Vec::new();
+	let mut chars = source.char_indices().peekable();
+	while let Some((start, c)) = chars.next() {
+		let token = match c {
+			c if c.is_whitespace() => continue,
+			'(' => Token::Open,
+			')' => Token::Close,
+			'0'..='9' => {
+				let mut end = start + 1;
+				while let Some((i, '0'..='9')) = chars.peek() {
+					end = i + 1;
+					chars.next();
+				}
+				let number = &source[start..end];
+				Token::Int(
+					number
+						.parse()
+						.map_err(|_| ExprError(format!("number too large: {number}")))?,
+				)
+			}
+			'"' => Token::Str(read_quoted(&mut chars, '"')?),
+			'\'' => match read_quoted(&mut chars, '\'')?.as_slice() {
+				&[byte] => Token::Int(byte as i64),
+				_ => return Err(ExprError("character literals must be one byte".into())),
+			},
+			c if c.is_ascii_alphabetic() || c == '_' => {
+				let mut end = start + 1;
+				while let Some(&(i, c)) = chars.peek() {
+					if !(c.is_ascii_alphanumeric() || c == '_') {
+						break;
+					}
+					end = i + 1;
+					chars.next();
+				}
+				match &source[start..end] {
+					"contains" => Token::Op(Op::Contains),
+					name => Token::Ident(name.to_owned()),
+				}
+			}
+			_ => {
+				let next = chars.peek().map(|&(_, c)| c);
+				let (token, len) = match (c, next) {
+					('=', Some('=')) => (Token::Op(Op::Eq), 2),
+					('!', Some('=')) => (Token::Op(Op::Ne), 2),
+					('<', Some('=')) => (Token::Op(Op::Le), 2),
+					('>', Some('=')) => (Token::Op(Op::Ge), 2),
+					('&', Some('&')) => (Token::Op(Op::And), 2),
+					('|', Some('|')) => (Token::Op(Op::Or), 2),
+					('<', _) => (Token::Op(Op::Lt), 1),
+					('>', _) => (Token::Op(Op::Gt), 1),
+					('!', _) => (Token::Not, 1),
+					_ => return Err(ExprError(format!("unexpected character '{c}'"))),
+				};
+				if len == 2 {
+					chars.next();
+				}
+				token
+			}
+		};
+		tokens.push(token);
+	}
+	Ok(tokens)
+}
+
+fn read_quoted(chars: &mut Peekable<CharIndices>, quote: char) -> Result<Vec<u8>, ExprError> {
+	let mut bytes = Vec::new();
+	loop {
+		let c = match chars.next() {
+			Some((_, c)) if c == quote => return Ok(bytes),
+			Some((_, '\\')) => match chars.next() {
+				Some((_, 'n')) => '\n',
+				Some((_, 't')) => '\t',
+				Some((_, '0')) => '\0',
+				Some((_, c)) => c,
+				None => break,
+			},
+			Some((_, c)) => c,
+			None => break,
+		};
+		bytes.extend(c.to_string().as_bytes());
+	}
+	Err(ExprError(format!("missing closing {quote}")))
+}
+
+impl fmt::Display for Expr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.source)
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Int(n) => write!(f, "{n}"),
+			Value::Str(s) => write!(f, "{:?}", String::from_utf8_lossy(s)),
+		}
+	}
+}
+
+impl fmt::Display for ExprError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.0)
+	}
+}
+
+impl std::error::Error for ExprError {}
@@ -0,0 +1,221 @@
+use std::{
+	fmt::Write,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use crate::{SandWormInterpreter, State};
+
+/// how long a program may run when it has no `.steps` file
+pub const DEFAULT_STEP_LIMIT: usize = 10_000_000;
+/// lines of unchanged output shown around each difference
+const DIFF_CONTEXT: usize = 2;
+/// diff lines shown before the rest is left out
+const DIFF_LINES: usize = 40;
+/// the largest table of line pairs to compare, about 8 MiB
+const DIFF_TABLE_CELLS: usize = 1 << 20;
+
+/// a program with the output it should produce, from `name.worm` and its sidecar files:
+/// `name.out` holds the expected output, `name.in` the input if there is any,
+/// and `name.steps` how many steps to run for programs that never end
+#[derive(Debug, Clone)]
+pub struct GoldenTest {
+	pub name: String,
+	pub path: PathBuf,
+	pub input: Vec<u8>,
+	pub expected: Vec<u8>,
+	/// the output is compared after this many steps, the program doesn't have to end
+	pub steps: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GoldenResult {
+	pub steps: usize,
+	pub output: Vec<u8>,
+	/// what went wrong, `None` if the test passed
+	pub failure: Option<String>,
+}
+
+impl GoldenTest {
+	/// every `.worm` file in `dir` that has a `.out` file, sorted by name
+	pub fn find(dir: &Path) -> io::Result<Vec<Self>> {
+		let mut tests = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let path = entry?.path();
+			if path.extension().is_none_or(|ext| ext != "worm") {
+				continue;
+			}
+			let expected = match fs::read(path.with_extension("out")) {
+				Ok(expected) => expected,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+				Err(err) => return Err(err),
+			};
+			let input = match fs::read(path.with_extension("in")) {
+				Ok(input) => input,
+				Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+				Err(err) => return Err(err),
+			};
+			let steps = match fs::read_to_string(path.with_extension("steps")) {
+				Ok(steps) => Some(steps.trim().parse().map_err(|_| {
+					io::Error::new(
+						io::ErrorKind::InvalidData,
+						format!(
+							"{}: not a step count",
+							path.with_extension("steps").display()
+						),
+					)
+				})?),
+				Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+				Err(err) => return Err(err),
+			};
+			let name = path
+				.file_stem()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.into();
+			tests.push(Self {
+				name,
+				path,
+				input,
+				expected,
+				steps,
+			});
+		}
+		tests.sort_by(|a, b| a.name.cmp(&b.name));
+		Ok(tests)
+	}
+
+	pub fn run(&self) -> GoldenResult {
+		let fail = |steps, output, failure: String| GoldenResult {
+			steps,
+			output,
+			failure: Some(failure),
+		};
+		let source = match fs::read_to_string(&self.path) {
+			Ok(source) => source,
+			Err(err) => return fail(0, Vec::new(), format!("could not read program: {err}")),
+		};
+		let mut interpreter = match SandWormInterpreter::new(&source, self.input.clone()) {
+			Ok(interpreter) => interpreter,
+			Err(err) => return fail(0, Vec::new(), err.to_string()),
+		};
+		let result = interpreter.step(self.steps.unwrap_or(DEFAULT_STEP_LIMIT));
+		let steps = interpreter.steps();
+		let output = interpreter.output().to_vec();
+		if let Err(err) = result {
+			return fail(steps, output, format!("error at step {steps}: {err}"));
+		}
+		if interpreter.state() == State::Deadlocked {
+			return fail(steps, output, format!("deadlocked at step {steps}"));
+		}
+		if self.steps.is_none() && interpreter.state() == State::Running {
+			return fail(
+				steps,
+				output,
+				format!("still running after {steps} steps, add a .steps file if it never ends"),
+			);
+		}
+		if output != self.expected {
+			let diff = diff(&self.expected, &output);
+			return fail(steps, output, format!("output differs:\n{diff}"));
+		}
+		GoldenResult {
+			steps,
+			output,
+			failure: None,
+		}
+	}
+}
+
+/// line by line difference, with `-` for expected lines that are missing and `+` for unexpected ones.
+/// bytes that aren't printable are escaped. when too many lines differ to compare them all,
+/// only the first differing line is shown
+pub fn diff(expected: &[u8], actual: &[u8]) -> String {
+	let old: Vec<_> = expected.split(|&b| b == b'\n').collect();
+	let new: Vec<_> = actual.split(|&b| b == b'\n').collect();
+	let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+	let suffix = old[prefix..]
+		.iter()
+		.rev()
+		.zip(new[prefix..].iter().rev())
+		.take_while(|(a, b)| a == b)
+		.count();
+	let (old_middle, new_middle) = (
+		&old[prefix..old.len() - suffix],
+		&new[prefix..new.len() - suffix],
+	);
+
+	let mut lines: Vec<_> = old[..prefix].iter().map(|&line| (' ', line)).collect();
+	let too_big = (old_middle.len() + 1) * (new_middle.len() + 1) > DIFF_TABLE_CELLS;
+	if too_big {
+		lines.extend(old_middle.first().map(|&line| ('-', line)));
+		lines.extend(new_middle.first().map(|&line| ('+', line)));
+	} else {
+		lines.extend(changed_lines(old_middle, new_middle));
+		lines.extend(old[old.len() - suffix..].iter().map(|&line| (' ', line)));
+	}
+
+	// every line within `DIFF_CONTEXT` of a change
+	let mut shown = vec![false; lines.len()];
+	for (i, _) in lines
+		.iter()
+		.enumerate()
+		.filter(|(_, (sign, _))| *sign != ' ')
+	{
+		let end = (i + DIFF_CONTEXT + 1).min(lines.len());
+		shown[i.saturating_sub(DIFF_CONTEXT)..end].fill(true);
+	}
+	let mut out = String::new();
+	let mut printed = 0;
+	let mut skipped = false;
+	for (i, (sign, line)) in lines.iter().enumerate() {
+		if !shown[i] {
+			skipped = true;
+			continue;
+		}
+		if printed == DIFF_LINES {
+			_ = writeln!(out, "...");
+			return out;
+		}
+		if skipped && printed > 0 {
+			_ = writeln!(out, "...");
+		}
+		skipped = false;
+		_ = writeln!(out, "{sign} {}", line.escape_ascii());
+		printed += 1;
+	}
+	if too_big {
+		_ = writeln!(out, "... too many lines differ to show the rest");
+	}
+	out
+}
+
+/// the lines of `old` and `new` marked as kept, removed or added, from their longest common subsequence
+fn changed_lines<'a>(old: &[&'a [u8]], new: &[&'a [u8]]) -> Vec<(char, &'a [u8])> {
+	// longest common subsequence lengths of every pair of suffixes
+	let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			lcs[i][j] = if old[i] == new[j] {
+				lcs[i + 1][j + 1] + 1
+			} else {
+				lcs[i + 1][j].max(lcs[i][j + 1])
+			};
+		}
+	}
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old[i] == new[j] {
+			lines.push((' ', old[i]));
+			(i, j) = (i + 1, j + 1);
+		} else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+			lines.push(('-', old[i]));
+			i += 1;
+		} else {
+			lines.push(('+', new[j]));
+			j += 1;
+		}
+	}
+	lines
+}
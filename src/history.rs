@@ -0,0 +1,120 @@
+use std::{collections::VecDeque, mem::size_of};
+
+use crate::{SandWormInterpreter, State, WormError};
+
+/// earlier interpreter states, kept as periodic checkpoints.
+/// going back restores the closest checkpoint and steps forward from there.
+/// the oldest checkpoints are dropped to stay within a memory budget,
+/// no matter how long the program runs or how much it prints
+#[derive(Debug)]
+pub struct History {
+	checkpoints: VecDeque<Checkpoint>,
+	interval: usize,
+	budget: usize,
+	/// estimated bytes held by all checkpoints
+	size: usize,
+}
+
+/// an interpreter without its output. output only ever grows, so the
+/// output at the time is the start of the current one
+#[derive(Debug)]
+struct Checkpoint {
+	interpreter: SandWormInterpreter,
+	output_len: usize,
+	size: usize,
+}
+
+impl Default for History {
+	fn default() -> Self {
+		Self::new(64, 64 << 20)
+	}
+}
+
+impl History {
+	/// keep a checkpoint every `interval` turns, in at most about `budget` bytes
+	pub fn new(interval: usize, budget: usize) -> Self {
+		Self {
+			checkpoints: VecDeque::new(),
+			interval: interval.max(1),
+			budget,
+			size: 0,
+		}
+	}
+
+	/// estimated bytes held by the checkpoints
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// call with every new state, saves a checkpoint when one is due.
+	/// the newest checkpoint is always kept, even if it is over budget alone
+	pub fn record(&mut self, interpreter: &SandWormInterpreter) {
+		let due = self
+			.checkpoints
+			.back()
+			.is_none_or(|last| interpreter.turns() >= last.interpreter.turns() + self.interval);
+		if !due {
+			return;
+		}
+		let output_len = interpreter.output.len();
+		let interpreter = interpreter.clone_without_output();
+		let checkpoint = Checkpoint {
+			size: size(&interpreter),
+			interpreter,
+			output_len,
+		};
+		self.size += checkpoint.size;
+		self.checkpoints.push_back(checkpoint);
+		while self.size > self.budget && self.checkpoints.len() > 1 {
+			let oldest = self.checkpoints.pop_front().unwrap();
+			self.size -= oldest.size;
+		}
+	}
+
+	/// restore the state from `n` turns ago, or the oldest one still remembered.
+	/// returns how many turns were undone
+	pub fn back(
+		&mut self,
+		interpreter: &mut SandWormInterpreter,
+		n: usize,
+	) -> Result<usize, WormError> {
+		let target = interpreter.turns().saturating_sub(n);
+		while let Some(last) = self.checkpoints.back() {
+			if last.interpreter.turns() <= target || self.checkpoints.len() == 1 {
+				break;
+			}
+			let last = self.checkpoints.pop_back().unwrap();
+			self.size -= last.size;
+		}
+		let Some(checkpoint) = self.checkpoints.back() else {
+			return Ok(0);
+		};
+		let target = target
+			.max(checkpoint.interpreter.turns())
+			.min(interpreter.turns());
+		let undone = interpreter.turns() - target;
+		let mut restored = checkpoint.interpreter.clone();
+		restored.output = interpreter.output[..checkpoint.output_len].to_vec();
+		*interpreter = restored;
+		while interpreter.turns() < target && interpreter.state() == State::Running {
+			interpreter.step_once()?;
+		}
+		Ok(undone)
+	}
+}
+
+/// a rough count of the bytes an interpreter holds, not counting the input it shares with its clones
+fn size(interpreter: &SandWormInterpreter) -> usize {
+	let grid = interpreter.program.len() * (size_of::<Vec<u8>>() + interpreter.width);
+	let worms: usize = (interpreter.worms.iter())
+		.map(|worm| {
+			size_of_val(worm)
+				+ worm.body.len() * size_of::<(usize, usize)>()
+				+ worm.values.len()
+				+ worm.segments.len() * size_of::<((usize, usize), usize)>() * 2
+				+ worm.worm_in.len()
+				+ worm.worm_out.len()
+		})
+		.sum();
+	size_of::<SandWormInterpreter>() + grid + worms
+}
@@ -0,0 +1,105 @@
+use std::{
+	fmt,
+	io::{self, ErrorKind, Read},
+	ops::Deref,
+	sync::MutexGuard,
+};
+
+/// input bytes for the `?` instruction, read lazily from an optional source.
+/// everything read so far is kept so it can be shown or read again
+#[derive(Default)]
+pub struct Input {
+	buffer: Vec<u8>,
+	source: Option<Box<dyn Read + Send>>,
+	/// bytes added while the source is still being read, they go after it
+	queued: Vec<u8>,
+}
+
+impl Input {
+	pub fn new(buffer: Vec<u8>) -> Self {
+		Self {
+			buffer,
+			source: None,
+			queued: Vec::new(),
+		}
+	}
+
+	pub fn from_reader(reader: impl Read + Send + 'static) -> Self {
+		Self::with_reader(Vec::new(), reader)
+	}
+
+	/// `buffer` is read first, then `reader`
+	pub fn with_reader(buffer: Vec<u8>, reader: impl Read + Send + 'static) -> Self {
+		Self {
+			buffer,
+			source: Some(Box::new(reader)),
+			queued: Vec::new(),
+		}
+	}
+
+	/// get the byte at `index`, blocking on the source until it is available.
+	/// returns `None` once the source has run out
+	pub fn get(&mut self, index: usize) -> io::Result<Option<u8>> {
+		while self.buffer.len() <= index {
+			let Some(source) = self.source.as_mut() else {
+				return Ok(None);
+			};
+			let mut byte = [0];
+			match source.read_exact(&mut byte) {
+				Ok(()) => self.buffer.push(byte[0]),
+				Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+					self.source = None;
+					self.buffer.append(&mut self.queued);
+				}
+				Err(err) => return Err(err),
+			}
+		}
+		Ok(Some(self.buffer[index]))
+	}
+
+	/// add bytes after everything else, including the rest of the source
+	pub fn extend(&mut self, bytes: &[u8]) {
+		if self.source.is_some() {
+			self.queued.extend(bytes);
+		} else {
+			self.buffer.extend(bytes);
+		}
+	}
+
+	/// true if there is nothing at `index` or after it, and nothing more to read
+	pub fn is_exhausted(&self, index: usize) -> bool {
+		self.source.is_none() && index >= self.buffer.len()
+	}
+
+	/// all bytes that have been read so far
+	pub fn buffered(&self) -> &[u8] {
+		&self.buffer
+	}
+
+	/// bytes added with `extend` that are waiting for the source to run out
+	pub fn queued(&self) -> &[u8] {
+		&self.queued
+	}
+}
+
+/// the input read so far, from `SandWormInterpreter::input`.
+/// the input stays locked until this is dropped
+pub struct Buffered<'a>(pub(crate) MutexGuard<'a, Input>);
+
+impl Deref for Buffered<'_> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		self.0.buffered()
+	}
+}
+
+impl fmt::Debug for Input {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Input")
+			.field("buffer", &self.buffer)
+			.field("source", &self.source.as_ref().map(|_| "dyn Read"))
+			.field("queued", &self.queued)
+			.finish()
+	}
+}
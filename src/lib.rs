@@ -0,0 +1,837 @@
+use std::{
+	collections::{HashMap, VecDeque},
+	io::Read,
+	mem,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+mod breakpoint;
+mod cycle;
+mod error;
+mod expr;
+mod golden;
+mod history;
+mod input;
+mod parse;
+mod render;
+mod snapshot;
+mod trace;
+
+pub use breakpoint::Breakpoint;
+pub use cycle::{Cycle, CycleDetector, CycleMode};
+pub use error::WormError;
+pub use expr::{Expr, ExprError, Value};
+pub use golden::{diff, GoldenResult, GoldenTest, DEFAULT_STEP_LIMIT};
+pub use history::History;
+pub use input::{Buffered, Input};
+pub use parse::{parse, Diagnostic, Parsed, Severity};
+pub use render::{draw_grid, Cell, Occupancy, Viewport};
+pub use trace::{StepInfo, Turn};
+
+/// the most cells an unbounded grid is allowed to grow to
+pub const MAX_GRID_CELLS: usize = 1 << 26;
+
+/// clones share the same input, so they will read the same bytes
+#[derive(Debug, Clone)]
+pub struct SandWormInterpreter {
+	/// the grid, except under living worm bodies where the worm holds the values
+	program: Vec<Vec<u8>>,
+	width: usize,
+	height: usize,
+	/// where the top left corner of the source ended up after the grid grew
+	origin: (usize, usize),
+	/// one worm for every `@` in the source, taking turns in this order
+	worms: Vec<Worm>,
+	/// index of the worm that moves next
+	current: usize,
+	/// turns in a row where the worm could not move because another worm was in the way
+	blocked_turns: usize,
+	topology: Topology,
+	input: Arc<Mutex<Input>>,
+	input_index: usize,
+	output: Vec<u8>,
+	state: State,
+	steps: usize,
+	/// every turn taken since this interpreter was created or loaded, including
+	/// blocked ones and the one leaving the grid, which `steps` leaves out
+	turns: usize,
+	last_step: StepInfo,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Worm {
+	/// body locations, from the tail to the neck, starting at `tail`
+	body: Vec<(usize, usize)>,
+	/// locations before this have already been left behind, and are removed in batches
+	tail: usize,
+	/// the value on each body segment, lined up with `body`. moving only changes
+	/// the locations, so the values stay put until the worm grows or shrinks
+	values: Vec<u8>,
+	/// body location to segment number, counting every segment the worm has ever had.
+	/// when segments share a cell, the newest one
+	segments: HashMap<(usize, usize), usize>,
+	/// segments that have left the tail, the number of `body[tail]`
+	dropped: usize,
+	head: (usize, usize),
+	/// queue for outputting commands at the back of the worm
+	worm_out: VecDeque<u8>,
+	worm_in: Vec<u8>,
+	direction: Direction,
+	alive: bool,
+	/// the head has run into the worm's own body, so segments can share a cell.
+	/// while tangled the grid holds the body values, as the original interpreter kept them,
+	/// and `values` is read back from it after every change
+	tangled: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+	Up,
+	Down,
+	Left,
+	#[default]
+	Right,
+}
+
+/// what happens when the worm moves past the edge of the grid
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topology {
+	/// the program ends
+	#[default]
+	Bounded,
+	/// the worm comes back in at the opposite edge
+	Torus,
+	/// the grid grows to make room, as if it was surrounded by empty space
+	Unbounded,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum State {
+	#[default]
+	Running,
+	EndOfProgram,
+	/// every worm is waiting for another one to get out of the way
+	Deadlocked,
+	/// a worm ran `.`, with this exit code
+	Halted(u8),
+}
+
+impl SandWormInterpreter {
+	pub fn new(source: &str, input: Vec<u8>) -> Result<Self, WormError> {
+		let parsed = parse(source);
+		if parsed.has_errors() {
+			return Err(WormError::Parse(parsed.diagnostics));
+		}
+		let Parsed {
+			program,
+			start_positions,
+			..
+		} = parsed;
+		let worms = start_positions
+			.into_iter()
+			.map(|head| Worm {
+				head,
+				alive: true,
+				..Worm::default()
+			})
+			.collect();
+
+		Ok(Self {
+			width: program[0].len(),
+			height: program.len(),
+			program,
+			origin: (0, 0),
+			worms,
+			current: 0,
+			blocked_turns: 0,
+			input: Arc::new(Mutex::new(Input::new(input))),
+			output: Vec::new(),
+			state: State::default(),
+			topology: Topology::default(),
+			input_index: 0,
+			steps: 0,
+			turns: 0,
+			last_step: StepInfo::default(),
+		})
+	}
+
+	/// a clone with an empty output, for keeping many copies around
+	fn clone_without_output(&self) -> Self {
+		Self {
+			program: self.program.clone(),
+			worms: self.worms.clone(),
+			input: Arc::clone(&self.input),
+			output: Vec::new(),
+			last_step: self.last_step.clone(),
+			..*self
+		}
+	}
+
+	pub fn run(&mut self) -> Result<(), WormError> {
+		while self.state == State::Running {
+			self.step_once()?;
+		}
+		Ok(())
+	}
+
+	pub fn step(&mut self, n: usize) -> Result<(), WormError> {
+		for _ in 0..n {
+			if self.state != State::Running {
+				break;
+			}
+			self.step_once()?;
+		}
+		Ok(())
+	}
+
+	/// give the next worm its turn. if this fails, the state is left as it was before the step
+	pub fn step_once(&mut self) -> Result<(), WormError> {
+		if self.state != State::Running {
+			return Ok(());
+		}
+		self.last_step = StepInfo {
+			step: self.steps,
+			worm: self.current,
+			..StepInfo::default()
+		};
+		let output_start = self.output.len();
+		let mut worm = mem::take(&mut self.worms[self.current]);
+		let turn = self.step_worm(&mut worm);
+		self.worms[self.current] = worm;
+		let turn = turn?;
+		self.turns += 1;
+		let worm = &self.worms[self.current];
+		self.last_step.turn = turn;
+		self.last_step.head = self.source_position(worm.head);
+		self.last_step.direction = worm.direction;
+		self.last_step.length = worm.body().len();
+		self.last_step.output = self.output[output_start..].to_vec();
+		match turn {
+			Turn::Moved => self.blocked_turns = 0,
+			Turn::Blocked => self.blocked_turns += 1,
+			Turn::LeftGrid => {
+				let mut worm = mem::take(&mut self.worms[self.current]);
+				self.settle(&mut worm);
+				self.worms[self.current] = worm;
+				self.blocked_turns = 0;
+			}
+		}
+		let alive = self.worms.iter().filter(|w| w.alive).count();
+		if let State::Halted(_) = self.state {
+			// the other worms stop where they are
+		} else if alive == 0 {
+			self.state = State::EndOfProgram;
+		} else if self.blocked_turns >= alive {
+			self.state = State::Deadlocked;
+		} else {
+			self.current = (1..=self.worms.len())
+				.map(|i| (self.current + i) % self.worms.len())
+				.find(|&i| self.worms[i].alive)
+				.unwrap_or(self.current);
+		}
+		Ok(())
+	}
+
+	/// `worm` has been taken out of `self.worms` for its turn
+	fn step_worm(&mut self, worm: &mut Worm) -> Result<Turn, WormError> {
+		if self.topology == Topology::Unbounded {
+			self.grow(worm)?;
+		}
+		let Some(front) = self.front(worm) else {
+			return Ok(Turn::LeftGrid);
+		};
+		// `worm` isn't in `self.worms` right now, so `worm_at` doesn't see it
+		if self.worm_at(front).is_some() {
+			return Ok(Turn::Blocked);
+		}
+		if !worm.tangled && (front == worm.head || worm.segments.contains_key(&front)) {
+			self.tangle(worm);
+		}
+		let instruction = self.program[front.1][front.0];
+		let mut dont_push_instruction = false;
+		self.last_step.instruction = Some(instruction);
+		let pushed_start = worm.worm_in.len();
+
+		match instruction {
+			b'0'..=b'9' => {
+				worm.worm_in.push(instruction - 48);
+			}
+			b'+' | b'-' | b'*' | b';' | b'%' => {
+				let a = self.shrink(worm)?;
+				worm.worm_out.push_front(instruction);
+				dont_push_instruction = true;
+				let b = self.shrink(worm)?;
+				worm.worm_in.push(arithmetic(instruction, a, b));
+			}
+			b'v' => worm.direction = Direction::Down,
+			b'^' => worm.direction = Direction::Up,
+			b'<' => worm.direction = Direction::Left,
+			b'>' => worm.direction = Direction::Right,
+			b'"' => {
+				let n = self.shrink(worm)?;
+				self.output.extend(n.to_string().as_bytes());
+			}
+			b'!' => {
+				let n = self.shrink(worm)?;
+				self.output.push(n);
+			}
+			b'?' => {
+				let val = lock_input(&self.input).get(self.input_index)?;
+				self.last_step.input.extend(val);
+				let val = val.unwrap_or_default();
+				self.input_index += 1;
+				worm.worm_in.push(val);
+			}
+			b'.' => {
+				let code = self.shrink(worm)?;
+				self.state = State::Halted(code);
+			}
+			b'&' => {
+				let val = self.read_number()?;
+				worm.worm_in.push(val);
+			}
+			b'=' => {
+				let last_val = worm.values.last().copied().unwrap_or_default();
+				worm.worm_in.push(last_val);
+			}
+			b'$' => {
+				self.shrink(worm)?;
+			}
+			b'#' => {
+				let len = worm.values.len();
+				if len >= 2 {
+					let swapped = [worm.values[len - 1], worm.values[len - 2]];
+					self.set_values(worm, len - 2, &swapped);
+				}
+			}
+			b'`' => {
+				let len = worm.values.len();
+				if len >= 3 {
+					let mut rotated = [0; 3];
+					rotated.copy_from_slice(&worm.values[len - 3..]);
+					rotated.rotate_left(1);
+					self.set_values(worm, len - 3, &rotated);
+				}
+			}
+			b'[' => {
+				let y = self.shrink(worm)?;
+				let x = self.shrink(worm)?;
+				let value = match self.cell(x, y) {
+					Some(pos) => worm.value_at(pos).unwrap_or_else(|| self.value(pos)),
+					None => 0,
+				};
+				worm.worm_in.push(value);
+			}
+			b']' => {
+				let y = self.shrink(worm)?;
+				let x = self.shrink(worm)?;
+				let value = self.shrink(worm)?;
+				if let Some(pos) = self.cell(x, y) {
+					self.put(worm, pos, value);
+				}
+			}
+			b'~' => {
+				let last_val = self.shrink(worm)?;
+				worm.worm_in.push((last_val == 0) as u8);
+			}
+			b'\\' => {
+				let val = self.shrink(worm)?;
+				if val != 0 {
+					worm.direction = match worm.direction {
+						Direction::Up => Direction::Left,
+						Direction::Down => Direction::Right,
+						Direction::Left => Direction::Up,
+						Direction::Right => Direction::Down,
+					}
+				}
+			}
+			b'/' => {
+				let val = self.shrink(worm)?;
+				if val != 0 {
+					worm.direction = match worm.direction {
+						Direction::Up => Direction::Right,
+						Direction::Down => Direction::Left,
+						Direction::Left => Direction::Down,
+						Direction::Right => Direction::Up,
+					}
+				}
+			}
+			b' ' | 0 => dont_push_instruction = true,
+			b'_' => worm.worm_in.push(b' '),
+			other => worm.worm_in.push(other),
+		}
+		if !dont_push_instruction {
+			worm.worm_out.push_front(instruction);
+		}
+		self.last_step.pushed = worm.worm_in[pushed_start..].to_vec();
+		self.move_to(worm, front);
+		self.steps += 1;
+		if worm.tangled
+			&& worm.segments.len() == worm.body().len()
+			&& !worm.segments.contains_key(&worm.head)
+		{
+			self.untangle(worm);
+		}
+		Ok(Turn::Moved)
+	}
+
+	/// a copy of the grid with the worm bodies filled in
+	pub fn program(&self) -> Vec<Vec<u8>> {
+		let mut program = self.program.clone();
+		for worm in self.worms.iter().filter(|w| w.alive) {
+			for (&(x, y), &value) in worm.body().iter().zip(&worm.values) {
+				program[y][x] = value;
+			}
+		}
+		program
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	/// where the top left corner of the source is in the grid.
+	/// only changes when an unbounded grid grows up or to the left
+	pub fn origin(&self) -> (usize, usize) {
+		self.origin
+	}
+
+	/// a grid location counted from the top left of the source, the way programs and users see it.
+	/// negative when an unbounded grid has grown above or to the left of the source
+	pub fn source_position(&self, (x, y): (usize, usize)) -> (i64, i64) {
+		(
+			x as i64 - self.origin.0 as i64,
+			y as i64 - self.origin.1 as i64,
+		)
+	}
+
+	/// the value on a grid cell, with the worm bodies filled in. `None` if it is off the grid
+	pub fn get(&self, pos: (usize, usize)) -> Option<u8> {
+		(pos.0 < self.width && pos.1 < self.height).then(|| self.value(pos))
+	}
+
+	/// `get` for a location known to be on the grid
+	fn value(&self, pos: (usize, usize)) -> u8 {
+		self.worms
+			.iter()
+			.filter(|w| w.alive)
+			.find_map(|w| w.value_at(pos))
+			.unwrap_or(self.program[pos.1][pos.0])
+	}
+
+	/// all worms, in the order they take turns. includes the ones that have left the grid
+	pub fn worms(&self) -> &[Worm] {
+		&self.worms
+	}
+
+	/// the worm that moves next
+	pub fn worm(&self) -> &Worm {
+		&self.worms[self.current]
+	}
+
+	/// index of the worm that moves next
+	pub fn current(&self) -> usize {
+		self.current
+	}
+
+	/// the living worm whose body or head is on `pos`
+	pub fn worm_at(&self, pos: (usize, usize)) -> Option<usize> {
+		self.worms
+			.iter()
+			.position(|w| w.alive && (w.head == pos || w.segments.contains_key(&pos)))
+	}
+
+	pub fn topology(&self) -> Topology {
+		self.topology
+	}
+
+	pub fn set_topology(&mut self, topology: Topology) {
+		self.topology = topology;
+	}
+
+	pub fn state(&self) -> State {
+		self.state
+	}
+
+	pub fn steps(&self) -> usize {
+		self.steps
+	}
+
+	/// turns taken since this interpreter was created or loaded, one for every `step_once`
+	/// while running, including turns where the worm was blocked or left the grid
+	pub fn turns(&self) -> usize {
+		self.turns
+	}
+
+	/// the input that has been read or provided so far
+	pub fn input(&self) -> Buffered<'_> {
+		Buffered(lock_input(&self.input))
+	}
+
+	pub fn input_index(&self) -> usize {
+		self.input_index
+	}
+
+	/// read any further input from `reader`, only when the program asks for it.
+	/// input pushed while the old source was still being read now waits for this one
+	pub fn set_input_source(&mut self, reader: impl Read + Send + 'static) {
+		let old = lock_input(&self.input);
+		let mut input = Input::with_reader(old.buffered().to_vec(), reader);
+		input.extend(old.queued());
+		drop(old);
+		self.input = Arc::new(Mutex::new(input));
+	}
+
+	/// add input after everything else, including anything the input source has yet to give
+	pub fn push_input(&mut self, bytes: &[u8]) {
+		lock_input(&self.input).extend(bytes);
+	}
+
+	pub fn output(&self) -> &[u8] {
+		&self.output
+	}
+
+	/// what happened in the last call to `step_once`
+	pub fn last_step(&self) -> &StepInfo {
+		&self.last_step
+	}
+
+	/// the instruction the current worm will execute next, or `None` if it is about to leave the grid
+	pub fn next_instruction(&self) -> Option<u8> {
+		self.front(self.worm()).map(|front| self.value(front))
+	}
+
+	fn move_to(&mut self, worm: &mut Worm, front: (usize, usize)) {
+		if let Some(input) = worm.worm_in.pop() {
+			if worm.tangled {
+				*self.get_mut(worm.head) = input;
+			}
+			worm.push_neck(worm.head, input);
+		} else {
+			if worm.tangled {
+				self.shift_values(worm, worm.head, worm.body().len());
+			}
+			let tail = worm.pop_tail();
+			let vacated = tail.unwrap_or(worm.head);
+			*self.get_mut(vacated) = worm.worm_out.pop_back().unwrap_or(b' ');
+			if tail.is_some() {
+				worm.push_position(worm.head);
+			}
+		}
+		worm.head = front;
+		*self.get_mut(front) = b'@';
+		if worm.tangled {
+			worm.read_values(&self.program);
+		}
+	}
+
+	/// copy the grid values of the first `n` segments forward by one, the last one onto `to`,
+	/// one cell at a time like the original interpreter, so segments sharing a cell see earlier copies
+	fn shift_values(&mut self, worm: &Worm, to: (usize, usize), n: usize) {
+		let mut next = to;
+		for &(x, y) in worm.body()[..n].iter().rev() {
+			self.program[next.1][next.0] = self.program[y][x];
+			next = (x, y);
+		}
+	}
+
+	/// the head ran into the body: move the values onto the grid, which keeps them from now on
+	fn tangle(&mut self, worm: &mut Worm) {
+		for (&(x, y), &value) in worm.body().iter().zip(&worm.values) {
+			self.program[y][x] = value;
+		}
+		worm.tangled = true;
+	}
+
+	/// the segments are on separate cells again, so `values` can keep them
+	fn untangle(&mut self, worm: &mut Worm) {
+		for &(x, y) in worm.body() {
+			self.program[y][x] = b'@';
+		}
+		worm.tangled = false;
+	}
+
+	/// replace the stack values from segment `start` on, in order
+	fn set_values(&mut self, worm: &mut Worm, start: usize, values: &[u8]) {
+		if worm.tangled {
+			for (&(x, y), &value) in worm.body()[start..].iter().zip(values) {
+				self.program[y][x] = value;
+			}
+			worm.read_values(&self.program);
+		} else {
+			worm.values[start..start + values.len()].copy_from_slice(values);
+		}
+	}
+
+	/// read a decimal number for `&`, skipping whitespace before it.
+	/// stops before the first byte that isn't a digit, wrapping around past 255.
+	/// gives 0 if there are no digits
+	fn read_number(&mut self) -> Result<u8, WormError> {
+		let mut input = lock_input(&self.input);
+		while let Some(byte @ (b' ' | b'\t' | b'\n' | b'\r')) = input.get(self.input_index)? {
+			self.last_step.input.push(byte);
+			self.input_index += 1;
+		}
+		let mut number = 0u8;
+		while let Some(byte @ b'0'..=b'9') = input.get(self.input_index)? {
+			self.last_step.input.push(byte);
+			self.input_index += 1;
+			number = number.wrapping_mul(10).wrapping_add(byte - b'0');
+		}
+		Ok(number)
+	}
+
+	/// get the front number and move the body forward (leaves the head where it was).
+	/// also shits out any queued instruction
+	fn shrink(&mut self, worm: &mut Worm) -> Result<u8, WormError> {
+		let len = worm.values.len();
+		if len != worm.body().len() {
+			return Err(WormError::Corrupted(format!(
+				"the worm has {len} values for {} body segments",
+				worm.body().len()
+			)));
+		}
+		let Some(ret) = worm.values.pop() else {
+			return Ok(0);
+		};
+		self.last_step.popped.push(ret);
+		if worm.tangled {
+			let neck = worm.body()[len - 1];
+			self.shift_values(worm, neck, len - 1);
+		}
+		if let Some(vacated) = worm.pop_tail() {
+			*self.get_mut(vacated) = worm.worm_out.pop_back().unwrap_or(b' ');
+		}
+		if worm.tangled {
+			worm.read_values(&self.program);
+		}
+		Ok(ret)
+	}
+
+	/// the grid location of source coordinates used by `[` and `]`, if it is on the grid
+	fn cell(&self, x: u8, y: u8) -> Option<(usize, usize)> {
+		let pos = (self.origin.0 + x as usize, self.origin.1 + y as usize);
+		(pos.0 < self.width && pos.1 < self.height).then_some(pos)
+	}
+
+	/// write a value for `]`. a body segment gets the new value on the stack,
+	/// and heads are left alone so every worm keeps its `@`
+	fn put(&mut self, worm: &mut Worm, pos: (usize, usize), value: u8) {
+		if worm.head == pos || self.worms.iter().any(|w| w.alive && w.head == pos) {
+			return;
+		}
+		let owner = std::iter::once(worm)
+			.chain(self.worms.iter_mut().filter(|w| w.alive))
+			.find(|w| w.segments.contains_key(&pos));
+		match owner {
+			Some(owner) if owner.tangled => {
+				self.program[pos.1][pos.0] = value;
+				owner.read_values(&self.program);
+			}
+			Some(owner) => *owner.value_at_mut(pos).unwrap() = value,
+			None => *self.get_mut(pos) = value,
+		}
+	}
+
+	/// write the body values onto the grid, for a worm that has stopped moving
+	fn settle(&mut self, worm: &mut Worm) {
+		for (&pos, &value) in worm.body().iter().zip(&worm.values) {
+			*self.get_mut(pos) = value;
+		}
+		worm.segments.clear();
+		worm.alive = false;
+		worm.tangled = false;
+	}
+
+	fn get_mut(&mut self, pos: (usize, usize)) -> &mut u8 {
+		&mut self.program[pos.1][pos.0]
+	}
+
+	/// add empty space if the worm is about to move off the grid.
+	/// grows by a fraction of the current size at a time to keep wandering worms cheap
+	fn grow(&mut self, worm: &mut Worm) -> Result<(), WormError> {
+		let (x, y) = worm.head;
+		let rows = (self.height / 2).max(16);
+		let cols = (self.width / 2).max(16);
+		let new_size = match worm.direction {
+			Direction::Up | Direction::Down => (self.height + rows) * self.width,
+			Direction::Left | Direction::Right => self.height * (self.width + cols),
+		};
+		if self.front(worm).is_none() && new_size > MAX_GRID_CELLS {
+			return Err(WormError::ResourceLimit(format!(
+				"grid would grow past {MAX_GRID_CELLS} cells"
+			)));
+		}
+		match worm.direction {
+			Direction::Up if y == 0 => {
+				let new_rows = (0..rows).map(|_| vec![0; self.width]);
+				self.program.splice(0..0, new_rows);
+				self.height += rows;
+				self.shift(worm, (0, rows));
+			}
+			Direction::Down if y + 1 >= self.height => {
+				self.program.resize(self.height + rows, vec![0; self.width]);
+				self.height += rows;
+			}
+			Direction::Left if x == 0 => {
+				for line in &mut self.program {
+					line.splice(0..0, vec![0; cols]);
+				}
+				self.width += cols;
+				self.shift(worm, (cols, 0));
+			}
+			Direction::Right if x + 1 >= self.width => {
+				for line in &mut self.program {
+					line.resize(self.width + cols, 0);
+				}
+				self.width += cols;
+			}
+			_ => (),
+		}
+		Ok(())
+	}
+
+	/// move everything on the grid after space was added at the top or left
+	fn shift(&mut self, worm: &mut Worm, (dx, dy): (usize, usize)) {
+		for worm in self.worms.iter_mut().chain([worm]) {
+			for pos in worm.body.iter_mut().chain([&mut worm.head]) {
+				*pos = (pos.0 + dx, pos.1 + dy);
+			}
+			if worm.alive {
+				worm.segments = (worm.body().iter().copied()).zip(worm.dropped..).collect();
+			}
+		}
+		self.origin = (self.origin.0 + dx, self.origin.1 + dy);
+	}
+
+	/// the cell the worm is about to move into, if it is on the grid
+	fn front(&self, worm: &Worm) -> Option<(usize, usize)> {
+		let (x, y) = worm.head;
+		let (width, height) = (self.width, self.height);
+		let front = match self.topology {
+			Topology::Bounded | Topology::Unbounded => match worm.direction {
+				Direction::Up => (x, y.checked_sub(1)?),
+				Direction::Down => (x, y + 1),
+				Direction::Left => (x.checked_sub(1)?, y),
+				Direction::Right => (x + 1, y),
+			},
+			Topology::Torus => match worm.direction {
+				Direction::Up => (x, (y + height - 1) % height),
+				Direction::Down => (x, (y + 1) % height),
+				Direction::Left => ((x + width - 1) % width, y),
+				Direction::Right => ((x + 1) % width, y),
+			},
+		};
+		(front.0 < width && front.1 < height).then_some(front)
+	}
+}
+
+impl Worm {
+	/// body locations, from the tail to the neck
+	pub fn body(&self) -> &[(usize, usize)] {
+		&self.body[self.tail..]
+	}
+
+	/// the stack, from the tail to the top of the stack at the neck
+	pub fn values(&self) -> &[u8] {
+		&self.values
+	}
+
+	pub fn head(&self) -> (usize, usize) {
+		self.head
+	}
+
+	/// values waiting to be added to the front of the worm
+	pub fn worm_in(&self) -> &[u8] {
+		&self.worm_in
+	}
+
+	/// instructions waiting to be left behind the tail, the next one is last
+	pub fn worm_out(&self) -> &VecDeque<u8> {
+		&self.worm_out
+	}
+
+	pub fn direction(&self) -> Direction {
+		self.direction
+	}
+
+	/// false once the worm has left the grid
+	pub fn is_alive(&self) -> bool {
+		self.alive
+	}
+
+	/// how many segments have left the tail so far, which makes `body()[0]` segment number `first_segment()`
+	pub fn first_segment(&self) -> usize {
+		self.dropped
+	}
+
+	/// the stack value of the body segment on `pos`
+	pub fn value_at(&self, pos: (usize, usize)) -> Option<u8> {
+		let segment = self.segments.get(&pos)?;
+		Some(self.values[segment - self.dropped])
+	}
+
+	fn value_at_mut(&mut self, pos: (usize, usize)) -> Option<&mut u8> {
+		let segment = self.segments.get(&pos)?;
+		Some(&mut self.values[segment - self.dropped])
+	}
+
+	/// add a segment at the neck, holding `value`
+	fn push_neck(&mut self, pos: (usize, usize), value: u8) {
+		self.push_position(pos);
+		self.values.push(value);
+	}
+
+	/// move the segments forward by one, with the neck ending up on `pos`.
+	/// the values stay in the same order, so only the locations change
+	fn push_position(&mut self, pos: (usize, usize)) {
+		self.segments.insert(pos, self.dropped + self.body().len());
+		self.body.push(pos);
+	}
+
+	/// the body values of a tangled worm, from the grid
+	fn read_values(&mut self, program: &[Vec<u8>]) {
+		for (value, &(x, y)) in self.values.iter_mut().zip(&self.body[self.tail..]) {
+			*value = program[y][x];
+		}
+	}
+
+	/// remove the tail location, returning the cell it leaves behind
+	fn pop_tail(&mut self) -> Option<(usize, usize)> {
+		let tail = *self.body().first()?;
+		// a tangled worm can have a newer segment on the same cell
+		if self.segments.get(&tail) == Some(&self.dropped) {
+			self.segments.remove(&tail);
+		}
+		self.dropped += 1;
+		self.tail += 1;
+		if self.tail >= 32 && self.tail * 2 >= self.body.len() {
+			self.body.drain(..self.tail);
+			self.tail = 0;
+		}
+		Some(tail)
+	}
+}
+
+/// the input shared between clones. nothing can panic halfway through changing it,
+/// so it is still fine to use after a panic elsewhere poisoned the lock
+fn lock_input(input: &Mutex<Input>) -> MutexGuard<'_, Input> {
+	input.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// apply a two-value instruction, `a` being the value popped first.
+/// dividing by zero gives zero, for both `;` and `%`
+fn arithmetic(instruction: u8, a: u8, b: u8) -> u8 {
+	match instruction {
+		b'+' => a.wrapping_add(b),
+		b'-' => a.wrapping_sub(b),
+		b'*' => a.wrapping_mul(b),
+		b';' => a.checked_div(b).unwrap_or(0),
+		b'%' => a.checked_rem(b).unwrap_or(0),
+		_ => unreachable!("{} is not arithmetic", instruction as char),
+	}
+}
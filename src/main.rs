@@ -1,321 +1,224 @@
-use std::{env, fs, io::stdin, process::exit};
-
-use owo_colors::OwoColorize;
-
-#[derive(Debug)]
-struct SandWormInterpreter {
-	program: Vec<Vec<u8>>,
-	width: usize,
-	height: usize,
-	/// worm body locations
-	worm: Vec<(usize, usize)>,
-	worm_head: (usize, usize),
-	/// queue for outputting commands at the back of the worm
-	worm_out: Vec<u8>,
-	worm_in: Vec<u8>,
-	direction: Direction,
-	input: Vec<u8>,
-	input_index: usize,
-	output: Vec<u8>,
-	state: State,
-	steps: usize,
+use std::{
+	env,
+	fs::{self, File},
+	io::{stdin, stdout, BufReader, BufWriter, Write},
+	path::Path,
+	process::exit,
+};
+
+use repl::Repl;
+use worm::{CycleDetector, CycleMode, GoldenTest, SandWormInterpreter, Severity, State, Topology};
+
+mod repl;
+
+#[derive(Debug, PartialEq)]
+enum Command {
+	Repl,
+	Run,
+	Check,
+	Test,
 }
 
-#[derive(Debug, Default)]
-enum Direction {
-	Up,
-	Down,
-	Left,
-	#[default]
-	Right,
-}
-
-#[derive(Debug, Default, PartialEq)]
-enum State {
-	#[default]
-	Running,
-	EndOfProgram,
-}
+const USAGE: &str = "usage: worm [run|check] [options] source_file [input_file]
+       worm test [directory]
+commands:
+  run       run to the end without the interactive ui
+  check     only report problems with the source
+  test      run every program that has a .out file and compare the output, in programs/ by default
+options:
+  --wrap    the grid wraps around at the edges instead of ending the program
+  --grow    the grid grows when the worm moves past the edges
+  --trace FILE  write a line of JSON to FILE for every step
+  --cycles MODE stop when the program gets stuck in a loop. MODE is exact, or heuristic to save memory";
 
 fn main() {
-	let args: Vec<_> = env::args().collect();
-	if args.len() <= 1 {
-		println!("usage: worm source_file [input_file]");
+	let mut args = Vec::new();
+	let mut topology = Topology::default();
+	let mut trace_path = None;
+	let mut cycles = None;
+	let mut env_args = env::args().skip(1);
+	while let Some(arg) = env_args.next() {
+		match arg.as_str() {
+			"--wrap" => topology = Topology::Torus,
+			"--grow" => topology = Topology::Unbounded,
+			"--cycles" => match env_args.next().as_deref() {
+				Some("exact") => cycles = Some(CycleMode::Exact),
+				Some("heuristic") => cycles = Some(CycleMode::Heuristic),
+				_ => {
+					eprintln!("--cycles needs a mode, exact or heuristic\n{USAGE}");
+					exit(1);
+				}
+			},
+			"--trace" => match env_args.next() {
+				Some(path) => trace_path = Some(path),
+				None => {
+					eprintln!("--trace needs a file name\n{USAGE}");
+					exit(1);
+				}
+			},
+			option if option.starts_with("--") => {
+				eprintln!("unknown option {option}\n{USAGE}");
+				exit(1);
+			}
+			_ => args.push(arg),
+		}
+	}
+	let command = match args.first().map(String::as_str) {
+		Some("run") => Command::Run,
+		Some("check") => Command::Check,
+		Some("test") => Command::Test,
+		_ => Command::Repl,
+	};
+	if command != Command::Repl {
+		args.remove(0);
+	}
+	if command == Command::Test {
+		run_tests(Path::new(args.first().map_or("programs", String::as_str)));
+	}
+	if args.is_empty() {
+		println!("{USAGE}");
 		exit(0);
 	}
-	let filename = &args[1];
+	let filename = &args[0];
 	let source = fs::read_to_string(filename).unwrap_or_else(|err| {
-		println!("Error reading file: {err}");
+		eprintln!("Error reading file: {err}");
 		exit(1);
 	});
-	let input_data = args
-		.get(2)
-		.map(|path| {
-			fs::read(path).unwrap_or_else(|err| {
-				println!("Error reading file: {err}");
-				exit(1);
-			})
+	let parsed = worm::parse(&source);
+	let diagnostics: Vec<_> = parsed
+		.diagnostics
+		.iter()
+		// keep warnings out of the way when running as part of a pipeline
+		.filter(|d| command != Command::Run || d.severity == Severity::Error)
+		.map(|d| d.render(filename, &source))
+		.collect();
+	let diagnostics = diagnostics.join("\n");
+	if command == Command::Check || parsed.has_errors() {
+		eprint!("{diagnostics}");
+		exit(parsed.has_errors() as i32);
+	}
+	let input_file = args.get(1).map(|path| {
+		File::open(path).unwrap_or_else(|err| {
+			eprintln!("Error reading file: {err}");
+			exit(1);
 		})
-		.unwrap_or_default();
-
-	let mut interpreter = SandWormInterpreter::new(&source, input_data);
+	});
 
-	loop {
-		interpreter.show();
-		let mut input_text = String::new();
-		stdin().read_line(&mut input_text).unwrap();
-		let action: Vec<_> = input_text.trim().split_ascii_whitespace().collect();
-		if input_text.starts_with("input ") {
-			interpreter.input.extend(
-				&input_text
-					.strip_suffix('\n')
-					.unwrap_or(&input_text)
-					.as_bytes()[6..],
-			);
-			continue;
-		}
-		match action.as_slice() {
-			[] | ["step"] => interpreter.step_once(),
-			["step", num] => _ = num.parse().map(|n| interpreter.step(n)),
-			["run"] => interpreter.run(),
-			["q" | "exit" | "quit"] => break,
+	let trace = trace_path.map(|path| {
+		let file = File::create(&path).unwrap_or_else(|err| {
+			eprintln!("Error creating trace file {path}: {err}");
+			exit(1);
+		});
+		BufWriter::new(file)
+	});
 
-			_ => println!("{}", "unrecognised command".red()),
-		}
+	let mut interpreter = SandWormInterpreter::new(&source, Vec::new()).unwrap_or_else(|err| {
+		eprintln!("Error loading {filename}: {err}");
+		exit(1);
+	});
+	interpreter.set_topology(topology);
+	if let Some(file) = input_file {
+		interpreter.set_input_source(BufReader::new(file));
 	}
-}
-
-impl SandWormInterpreter {
-	fn new(source: &str, input: Vec<u8>) -> Self {
-		let (program, start_pos) = parse(source);
-
-		Self {
-			width: program[0].len(),
-			height: program.len(),
-			program,
-			worm: Vec::new(),
-			worm_head: start_pos,
-			worm_in: Vec::new(),
-			worm_out: Vec::new(),
-			input,
-			output: Vec::new(),
-			state: State::default(),
-			direction: Direction::default(),
-			input_index: 0,
-			steps: 0,
+	if command == Command::Run {
+		if args.len() < 2 {
+			interpreter.set_input_source(stdin());
 		}
+		run_batch(interpreter, trace, cycles.map(CycleDetector::new));
 	}
 
-	fn run(&mut self) {
-		while self.state == State::Running {
-			self.step_once();
-		}
+	let mut repl = Repl::new(interpreter);
+	if let Some(trace) = trace {
+		repl.set_trace(trace);
 	}
-
-	fn step(&mut self, n: usize) {
-		for _ in 0..n {
-			if self.state != State::Running {
-				break;
-			}
-			self.step_once();
-		}
+	if let Some(mode) = cycles {
+		repl.set_cycle_detection(Some(mode));
 	}
-
-	fn show(&self) {
-		// dbg!(&self);
-		print!("\x1B[2J"); // clear screen
-		println!(
-			"{:?}",
-			self.worm.iter().map(|p| self.get(*p)).collect::<Vec<_>>()
-		);
-		for (row, line) in self.program.iter().enumerate() {
-			for (col, &byte) in line.iter().enumerate() {
-				if self.worm.contains(&(col, row)) {
-					if byte < 10 {
-						print!("{:x}", byte.on_green());
-					} else {
-						print!("{}", "*".green().on_red());
-					}
-				} else if self.worm_head == (col, row) {
-					if byte == b'@' {
-						print!("{}", "@".on_yellow());
-					} else {
-						panic!("worm head corrupted");
-					}
-				} else if byte == 0 || byte == b' ' {
-					print!(" ");
-				} else if byte.is_ascii_alphanumeric() || byte.is_ascii_punctuation() {
-					print!("{}", byte as char);
-				} else {
-					print!("{}", "*".green());
-				}
-			}
-			println!();
-		}
-		println!("output: {}", String::from_utf8_lossy(&self.output));
-		println!("input: {}", String::from_utf8_lossy(&self.input));
-		println!("steps: {}", self.steps);
+	if !diagnostics.is_empty() {
+		repl.set_message(diagnostics);
+	}
+	if let Err(err) = repl.run() {
+		eprintln!("Error: {err}");
+		exit(1);
 	}
+}
 
-	fn step_once(&mut self) {
-		if self.state != State::Running {
-			return;
-		}
-		let front = self.front();
-		if front.0 >= self.width || front.1 >= self.height {
-			self.state = State::EndOfProgram;
-			return;
+/// run without the ui, writing output to stdout as soon as it is produced
+fn run_batch(
+	mut interpreter: SandWormInterpreter,
+	mut trace: Option<BufWriter<File>>,
+	mut cycles: Option<CycleDetector>,
+) -> ! {
+	let mut stdout = stdout().lock();
+	let mut written = 0;
+	if let Some(detector) = &mut cycles {
+		detector.check(&interpreter);
+	}
+	while interpreter.state() == State::Running {
+		if let Err(err) = interpreter.step_once() {
+			eprintln!("Error at step {}: {err}", interpreter.steps());
+			exit(1);
 		}
-		self.steps += 1;
-		let instruction = self.get(front);
-		let mut dont_push_instruction = false;
-
-		match instruction {
-			b'0'..=b'9' => {
-				self.worm_in.push(instruction - 48);
-			}
-			b'+' => {
-				let a = self.shrink();
-				self.worm_out.insert(0, instruction);
-				let b = self.shrink();
-				dont_push_instruction = true;
-				self.worm_in.push(a.wrapping_add(b));
-			}
-			b'-' => {
-				let a = self.shrink();
-				self.worm_out.insert(0, instruction);
-				dont_push_instruction = true;
-				let b = self.shrink();
-				self.worm_in.push(a.wrapping_sub(b));
-			}
-			b'v' => self.direction = Direction::Down,
-			b'^' => self.direction = Direction::Up,
-			b'<' => self.direction = Direction::Left,
-			b'>' => self.direction = Direction::Right,
-			b'"' => {
-				let n = self.shrink();
-				self.output.extend(n.to_string().as_bytes());
-			}
-			b'!' => {
-				let n = self.shrink();
-				self.output.push(n);
-			}
-			b'?' => {
-				let val = self
-					.input
-					.get(self.input_index)
-					.copied()
-					.unwrap_or_default();
-				self.input_index += 1;
-				self.worm_in.push(val);
-			}
-			b'=' => {
-				let last_val = self.worm.last().map(|&p| self.get(p)).unwrap_or_default();
-				self.worm_in.push(last_val);
-			}
-			b'~' => {
-				let last_val = self.shrink();
-				self.worm_in.push((last_val == 0) as u8);
-			}
-			b'\\' => {
-				let val = self.shrink();
-				if val != 0 {
-					self.direction = match self.direction {
-						Direction::Up => Direction::Left,
-						Direction::Down => Direction::Right,
-						Direction::Left => Direction::Up,
-						Direction::Right => Direction::Down,
-					}
-				}
-			}
-			b'/' => {
-				let val = self.shrink();
-				if val != 0 {
-					self.direction = match self.direction {
-						Direction::Up => Direction::Right,
-						Direction::Down => Direction::Left,
-						Direction::Left => Direction::Down,
-						Direction::Right => Direction::Up,
-					}
-				}
+		if let Some(trace) = &mut trace {
+			if let Err(err) = writeln!(trace, "{}", interpreter.last_step().to_json()) {
+				eprintln!("Error writing trace: {err}");
+				exit(1);
 			}
-			b' ' | 0 => dont_push_instruction = true,
-			b'_' => self.worm_in.push(b' '),
-			other => self.worm_in.push(other),
 		}
-		if !dont_push_instruction {
-			self.worm_out.insert(0, instruction);
-		}
-		self.move_to(front);
-	}
-
-	fn move_to(&mut self, front: (usize, usize)) {
-		if let Some(input) = self.worm_in.pop() {
-			*self.get_mut(self.worm_head) = input;
-			self.worm.push(self.worm_head);
-		} else {
-			let mut next = self.worm_head;
-			for body_segment in self.worm.iter_mut().rev() {
-				self.program[next.1][next.0] = self.program[body_segment.1][body_segment.0];
-				(*body_segment, next) = (next, *body_segment);
+		let cycle = cycles.as_mut().and_then(|d| d.check(&interpreter));
+		let new_output = &interpreter.output()[written..];
+		if let Some(cycle) = cycle {
+			// show what was printed before giving up
+			_ = stdout.write_all(new_output).and_then(|_| stdout.flush());
+			if let Some(trace) = &mut trace {
+				_ = trace.flush();
 			}
-			*self.get_mut(next) = self.worm_out.pop().unwrap_or(b' ');
+			eprintln!("{cycle}");
+			exit(1);
 		}
-		self.worm_head = front;
-		*self.get_mut(front) = b'@';
-	}
-
-	/// get the front number and move the body forward (leaves the head where it was).
-	/// also shits out any queued instruction
-	fn shrink(&mut self) -> u8 {
-		if let Some(neck) = self.worm.pop() {
-			let ret = self.get(neck);
-			let mut next = neck;
-			for body_segment in self.worm.iter_mut().rev() {
-				self.program[next.1][next.0] = self.program[body_segment.1][body_segment.0];
-				(*body_segment, next) = (next, *body_segment);
-			}
-			*self.get_mut(next) = self.worm_out.pop().unwrap_or(b' ');
-			ret
-		} else {
-			0
+		if new_output.is_empty() {
+			continue;
 		}
+		if let Err(err) = stdout.write_all(new_output).and_then(|_| stdout.flush()) {
+			eprintln!("Error writing output: {err}");
+			exit(1);
+		}
+		written += new_output.len();
 	}
-
-	fn get(&self, pos: (usize, usize)) -> u8 {
-		self.program[pos.1][pos.0]
-	}
-
-	fn get_mut(&mut self, pos: (usize, usize)) -> &mut u8 {
-		&mut self.program[pos.1][pos.0]
+	if let Some(Err(err)) = trace.as_mut().map(Write::flush) {
+		eprintln!("Error writing trace: {err}");
+		exit(1);
 	}
-
-	fn front(&self) -> (usize, usize) {
-		let mut front = self.worm_head;
-		match self.direction {
-			Direction::Up => front.1 = front.1.wrapping_sub(1),
-			Direction::Down => front.1 = front.1.saturating_add(1),
-			Direction::Left => front.0 = front.0.wrapping_sub(1),
-			Direction::Right => front.0 = front.0.saturating_add(1),
+	match interpreter.state() {
+		State::Deadlocked => {
+			eprintln!("Deadlocked at step {}", interpreter.steps());
+			exit(1);
 		}
-		front
+		State::Halted(code) => exit(code.into()),
+		_ => exit(0),
 	}
 }
 
-fn parse(source: &str) -> (Vec<Vec<u8>>, (usize, usize)) {
-	let mut program = Vec::new();
-	let mut width = 0;
-	let mut start_pos = (0, 0);
-	for (row, line) in source.lines().enumerate() {
-		width = width.max(line.len());
-		if let Some(col) = line.find('@') {
-			start_pos = (col, row);
+/// run the golden output tests in `dir`, exiting with 1 if any of them fail
+fn run_tests(dir: &Path) -> ! {
+	let tests = GoldenTest::find(dir).unwrap_or_else(|err| {
+		eprintln!("Error finding tests in {}: {err}", dir.display());
+		exit(1);
+	});
+	let mut failed = 0;
+	for test in &tests {
+		let result = test.run();
+		match result.failure {
+			None => println!("ok   {} ({} steps)", test.name, result.steps),
+			Some(failure) => {
+				failed += 1;
+				println!("FAIL {} ({} steps)", test.name, result.steps);
+				for line in failure.lines() {
+					println!("     {line}");
+				}
+			}
 		}
-		program.push(line.as_bytes().to_vec());
 	}
-	for line in &mut program {
-		line.resize(width, 0);
-	}
-
-	(program, start_pos)
+	println!("{} passed, {failed} failed", tests.len() - failed);
+	exit((failed > 0) as i32);
 }
@@ -0,0 +1,180 @@
+use std::fmt::Write;
+
+/// a program grid along with anything worth pointing out about its source
+#[derive(Debug, Clone)]
+pub struct Parsed {
+	pub program: Vec<Vec<u8>>,
+	/// every `@`, in reading order
+	pub start_positions: Vec<(usize, usize)>,
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub message: String,
+	/// line and column, counting from 1. columns are bytes, the same as grid cells
+	pub line: usize,
+	pub column: usize,
+	pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+impl Parsed {
+	pub fn has_errors(&self) -> bool {
+		self.diagnostics
+			.iter()
+			.any(|d| d.severity == Severity::Error)
+	}
+}
+
+impl Diagnostic {
+	fn new(severity: Severity, message: impl Into<String>, line: usize, column: usize) -> Self {
+		Self {
+			severity,
+			message: message.into(),
+			line,
+			column,
+			len: 1,
+		}
+	}
+
+	fn with_len(mut self, len: usize) -> Self {
+		self.len = len.max(1);
+		self
+	}
+
+	/// format like a compiler message, with the offending part of the source underlined
+	pub fn render(&self, filename: &str, source: &str) -> String {
+		let severity = match self.severity {
+			Severity::Error => "error",
+			Severity::Warning => "warning",
+		};
+		let line_text = source.split('\n').nth(self.line - 1).unwrap_or_default();
+		let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+		let line_number = self.line.to_string();
+		let gutter = " ".repeat(line_number.len());
+		// columns are bytes, but the excerpt is shown as characters
+		let bytes = line_text.as_bytes();
+		let start = (self.column - 1).min(bytes.len());
+		let end = (start + self.len).min(bytes.len());
+		let indent = String::from_utf8_lossy(&bytes[..start]).chars().count();
+		let width = String::from_utf8_lossy(&bytes[start..end]).chars().count();
+
+		let mut out = String::new();
+		_ = writeln!(out, "{severity}: {}", self.message);
+		_ = writeln!(out, "{gutter}--> {filename}:{}:{}", self.line, self.column);
+		_ = writeln!(out, "{gutter} |");
+		_ = writeln!(out, "{line_number} | {}", line_text.replace('\t', " "));
+		_ = writeln!(
+			out,
+			"{gutter} | {}{}",
+			" ".repeat(indent),
+			"^".repeat(width.max(1))
+		);
+		out
+	}
+}
+
+pub fn parse(source: &str) -> Parsed {
+	let mut program = Vec::new();
+	let mut diagnostics = Vec::new();
+	let mut width = 0;
+	let mut start_positions = Vec::new();
+	for (row, line) in source.split_inclusive('\n').enumerate() {
+		let line = line.strip_suffix('\n').unwrap_or(line);
+		let (line, crlf) = match line.strip_suffix('\r') {
+			Some(line) => (line, true),
+			None => (line, false),
+		};
+		let line_number = row + 1;
+		for (col, byte) in line.bytes().enumerate() {
+			if byte == b'@' {
+				start_positions.push((col, row));
+			}
+		}
+		check_line(line, crlf, line_number, &mut diagnostics);
+		width = width.max(line.len());
+		program.push(line.as_bytes().to_vec());
+	}
+	for line in &mut program {
+		line.resize(width, 0);
+	}
+	if width == 0 {
+		diagnostics.push(Diagnostic::new(Severity::Error, "program is empty", 1, 1));
+	} else if start_positions.is_empty() {
+		diagnostics.push(Diagnostic::new(
+			Severity::Error,
+			"no worm head '@' to start from",
+			1,
+			1,
+		));
+	}
+	diagnostics.sort_by_key(|d| (d.line, d.column));
+
+	Parsed {
+		program,
+		start_positions,
+		diagnostics,
+	}
+}
+
+fn check_line(line: &str, crlf: bool, line_number: usize, diagnostics: &mut Vec<Diagnostic>) {
+	let warn = |message: &str, column: usize| {
+		Diagnostic::new(Severity::Warning, message, line_number, column + 1)
+	};
+	for (col, byte) in line.bytes().enumerate() {
+		if byte == b'\t' {
+			diagnostics.push(warn(
+				"tab is not a space, it will be pushed as the value 9",
+				col,
+			));
+		}
+	}
+	for (col, c) in line.char_indices().filter(|(_, c)| !c.is_ascii()) {
+		diagnostics.push(
+			warn(
+				"non-ASCII character, each of its bytes takes up a separate cell",
+				col,
+			)
+			.with_len(c.len_utf8()),
+		);
+	}
+	let trimmed = line.trim_end_matches(' ');
+	if trimmed.len() < line.len() {
+		diagnostics
+			.push(warn("trailing whitespace", trimmed.len()).with_len(line.len() - trimmed.len()));
+	}
+	if crlf {
+		diagnostics.push(warn("line ends with CRLF, the CR is ignored", line.len()));
+	}
+	if looks_like_prose(line) {
+		diagnostics.push(
+			warn(
+				"this looks like prose, but it is part of the program. use '_' for spaces in text",
+				0,
+			)
+			.with_len(line.len()),
+		);
+	}
+}
+
+/// several words separated by single spaces, which would be written with `_` in worm code
+fn looks_like_prose(line: &str) -> bool {
+	let words: Vec<_> = line.split(' ').collect();
+	let word_like = words
+		.iter()
+		.filter(|word| {
+			word.len() >= 2
+				&& word
+					.chars()
+					.all(|c| c.is_alphabetic() || ",.;:!?'".contains(c))
+		})
+		.count();
+	word_like >= 3 && word_like * 2 > words.len()
+}
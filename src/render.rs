@@ -0,0 +1,172 @@
+use std::{
+	collections::VecDeque,
+	io::{self, Write},
+};
+
+use owo_colors::OwoColorize;
+
+use crate::SandWormInterpreter;
+
+/// what is on each grid cell, so drawing doesn't have to search the worms for every cell.
+/// kept in sync with the worms by following their heads and tails, instead of refilling it every frame
+#[derive(Debug, Clone, Default)]
+pub struct Occupancy {
+	width: usize,
+	height: usize,
+	origin: (usize, usize),
+	cells: Vec<Option<Cell>>,
+	/// the cells marked for each worm
+	marked: Vec<Marked>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Marked {
+	head: Option<(usize, usize)>,
+	/// body locations from the tail, the first one being segment `first` of the worm
+	body: VecDeque<(usize, usize)>,
+	first: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+	/// body segment of the worm with this index
+	Body(usize),
+	Head(usize),
+}
+
+impl Occupancy {
+	/// catch up with the worms' moves since the last update.
+	/// call `clear` first if the interpreter was replaced or went back in time
+	pub fn update(&mut self, interpreter: &SandWormInterpreter) {
+		let shape = (
+			interpreter.width(),
+			interpreter.height(),
+			interpreter.origin(),
+		);
+		if shape != (self.width, self.height, self.origin)
+			|| self.marked.len() != interpreter.worms().len()
+		{
+			self.width = shape.0;
+			self.height = shape.1;
+			self.origin = shape.2;
+			self.cells.clear();
+			self.cells.resize(self.width * self.height, None);
+			self.marked.clear();
+			self.marked
+				.resize(interpreter.worms().len(), Marked::default());
+		}
+		for (i, worm) in interpreter.worms().iter().enumerate() {
+			let marked = &mut self.marked[i];
+			let (first, body) = if worm.is_alive() {
+				(worm.first_segment(), worm.body())
+			} else {
+				(marked.first + marked.body.len(), &[][..])
+			};
+			let end = first + body.len();
+			if first < marked.first || end < marked.first + marked.body.len() {
+				// not a continuation of what is marked
+				self.clear();
+				return self.update(interpreter);
+			}
+			let marked = &mut self.marked[i];
+			if let Some(head) = marked.head.take() {
+				clear_cell(&mut self.cells, self.width, head, Cell::Head(i));
+			}
+			let gone = (first - marked.first).min(marked.body.len());
+			for pos in marked.body.drain(..gone) {
+				// a worm tangled in its own body can still have a newer segment there
+				if worm.value_at(pos).is_none() {
+					clear_cell(&mut self.cells, self.width, pos, Cell::Body(i));
+				}
+			}
+			marked.first = first;
+			for &(x, y) in &body[marked.body.len()..] {
+				self.cells[y * self.width + x] = Some(Cell::Body(i));
+				marked.body.push_back((x, y));
+			}
+		}
+		// heads last, so a segment leaving a cell doesn't clear the head that moved onto it
+		for (i, worm) in interpreter.worms().iter().enumerate() {
+			if worm.is_alive() {
+				let (x, y) = worm.head();
+				self.cells[y * self.width + x] = Some(Cell::Head(i));
+				self.marked[i].head = Some((x, y));
+			}
+		}
+	}
+
+	/// forget everything, the next update fills the map from scratch
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+
+	/// `None` for empty cells and cells off the grid
+	pub fn get(&self, (x, y): (usize, usize)) -> Option<Cell> {
+		if x >= self.width || y >= self.height {
+			return None;
+		}
+		self.cells[y * self.width + x]
+	}
+}
+
+/// unmark a cell, unless something else has been marked on it since
+fn clear_cell(cells: &mut [Option<Cell>], width: usize, (x, y): (usize, usize), cell: Cell) {
+	let slot = &mut cells[y * width + x];
+	if *slot == Some(cell) {
+		*slot = None;
+	}
+}
+
+/// the part of the grid that gets drawn, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+	pub x: usize,
+	pub y: usize,
+	pub width: usize,
+	pub height: usize,
+}
+
+/// draw the visible part of the grid with the worms highlighted.
+/// rows are separated by `\r\n` so this also works in raw mode
+pub fn draw_grid(
+	interpreter: &SandWormInterpreter,
+	occupancy: &Occupancy,
+	view: Viewport,
+	out: &mut impl Write,
+) -> io::Result<()> {
+	let rows = view.y..(view.y + view.height).min(interpreter.height());
+	let columns = view.x..(view.x + view.width).min(interpreter.width());
+	for row in rows.clone() {
+		if row != rows.start {
+			write!(out, "\r\n")?;
+		}
+		for col in columns.clone() {
+			let cell = occupancy.get((col, row));
+			let byte = match cell {
+				Some(Cell::Body(index)) => interpreter.worms()[index].value_at((col, row)),
+				_ => None,
+			};
+			let byte = byte
+				.or_else(|| interpreter.get((col, row)))
+				.unwrap_or_default();
+			match cell {
+				Some(Cell::Body(_)) if byte < 10 => write!(out, "{:x}", byte.on_green())?,
+				Some(Cell::Body(index)) if index == interpreter.current() => {
+					write!(out, "{}", "*".green().on_red())?
+				}
+				Some(Cell::Body(_)) => write!(out, "{}", "*".blue().on_red())?,
+				Some(Cell::Head(_)) if byte != b'@' => write!(out, "{}", (byte as char).on_red())?,
+				Some(Cell::Head(index)) if index == interpreter.current() => {
+					write!(out, "{}", "@".on_yellow())?
+				}
+				Some(Cell::Head(_)) => write!(out, "{}", "@".on_cyan())?,
+				None if byte == 0 || byte == b' ' => write!(out, " ")?,
+				None if byte.is_ascii_alphanumeric() || byte.is_ascii_punctuation() => {
+					write!(out, "{}", byte as char)?
+				}
+				None => write!(out, "{}", "*".green())?,
+			}
+		}
+	}
+	Ok(())
+}
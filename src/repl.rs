@@ -0,0 +1,633 @@
+use std::{
+	fs::{self, File},
+	io::{self, stdout, BufWriter, Write},
+	time::{Duration, Instant},
+};
+
+use crossterm::{
+	cursor,
+	event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+	execute, queue,
+	terminal::{self, ClearType},
+};
+use owo_colors::OwoColorize;
+use worm::{
+	draw_grid, Breakpoint, CycleDetector, CycleMode, Direction, Expr, History, Occupancy,
+	SandWormInterpreter, State, Viewport, WormError,
+};
+
+/// how many lines of watch output are kept for display
+const WATCH_LOG_LINES: usize = 50;
+const PANEL_WIDTH: usize = 32;
+/// how close the head gets to the edge of the view before it scrolls
+const SCROLL_MARGIN: usize = 4;
+/// steps between checking for a keypress while running
+const RUN_BATCH: usize = 1000;
+/// how often the screen is redrawn while running
+const RUN_FRAME_TIME: Duration = Duration::from_millis(100);
+const PLAY_SPEED: f64 = 10.0;
+/// shortest time between redraws while playing, at higher speeds several steps are taken per frame
+const PLAY_FRAME_TIME: Duration = Duration::from_millis(16);
+const HELP: &str = "s step  r run  p pause  b back  arrows scroll  f follow  : command  q quit";
+
+pub struct Repl {
+	interpreter: SandWormInterpreter,
+	history: History,
+	breakpoints: Vec<Breakpoint>,
+	watches: Vec<Expr>,
+	/// watch values from the last command, one line per step
+	watch_log: Vec<String>,
+	/// shown below the program until the next key
+	message: Option<String>,
+	occupancy: Occupancy,
+	/// top left corner of the visible part of the grid
+	view: (usize, usize),
+	/// scroll along with the head of the worm that moves next
+	follow: bool,
+	/// what has been typed after `:`
+	command: Option<String>,
+	quit: bool,
+	/// where a line of JSON is written for every step
+	trace: Option<BufWriter<File>>,
+	cycles: Option<CycleDetector>,
+}
+
+/// raw mode and the alternate screen, restored when dropped
+struct RawTerminal;
+
+impl RawTerminal {
+	fn enter() -> io::Result<Self> {
+		terminal::enable_raw_mode()?;
+		execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+		Ok(Self)
+	}
+}
+
+impl Drop for RawTerminal {
+	fn drop(&mut self) {
+		_ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+		_ = terminal::disable_raw_mode();
+	}
+}
+
+impl Repl {
+	pub fn new(interpreter: SandWormInterpreter) -> Self {
+		let mut history = History::default();
+		history.record(&interpreter);
+		Self {
+			interpreter,
+			history,
+			breakpoints: Vec::new(),
+			watches: Vec::new(),
+			watch_log: Vec::new(),
+			message: None,
+			occupancy: Occupancy::default(),
+			view: (0, 0),
+			follow: true,
+			command: None,
+			quit: false,
+			trace: None,
+			cycles: None,
+		}
+	}
+
+	pub fn set_message(&mut self, message: String) {
+		self.message = Some(message);
+	}
+
+	pub fn set_trace(&mut self, trace: BufWriter<File>) {
+		self.trace = Some(trace);
+	}
+
+	/// look for repeating states while stepping, starting from the current state
+	pub fn set_cycle_detection(&mut self, mode: Option<CycleMode>) {
+		self.cycles = mode.map(CycleDetector::new);
+		if let Some(detector) = &mut self.cycles {
+			detector.check(&self.interpreter);
+		}
+	}
+
+	pub fn run(mut self) -> io::Result<()> {
+		let _terminal = RawTerminal::enter()?;
+		while !self.quit {
+			self.show()?;
+			if let Event::Key(key) = event::read()? {
+				if key.kind != KeyEventKind::Release {
+					self.handle_key(key)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn handle_key(&mut self, key: KeyEvent) -> io::Result<()> {
+		if let Some(command) = &mut self.command {
+			match key.code {
+				KeyCode::Enter => {
+					let line = self.command.take().unwrap_or_default();
+					self.message = None;
+					self.watch_log.clear();
+					self.execute(&line)?;
+				}
+				KeyCode::Esc => self.command = None,
+				KeyCode::Backspace => _ = command.pop(),
+				KeyCode::Char(c) => command.push(c),
+				_ => (),
+			}
+			return Ok(());
+		}
+		if is_ctrl_c(key) {
+			self.quit = true;
+			return Ok(());
+		}
+		self.message = None;
+		self.watch_log.clear();
+		let (width, height) = self.view_size()?;
+		match key.code {
+			KeyCode::Char('s' | ' ') | KeyCode::Enter => _ = self.step(1),
+			KeyCode::Char('r') => self.run_until_paused()?,
+			KeyCode::Char('b') => self.back(1),
+			KeyCode::Char('f') => self.follow = true,
+			KeyCode::Char(':') => self.command = Some(String::new()),
+			KeyCode::Char('q') => self.quit = true,
+			KeyCode::Left | KeyCode::Char('h') => self.scroll(-1, 0),
+			KeyCode::Right | KeyCode::Char('l') => self.scroll(1, 0),
+			KeyCode::Up | KeyCode::Char('k') => self.scroll(0, -1),
+			KeyCode::Down | KeyCode::Char('j') => self.scroll(0, 1),
+			KeyCode::PageUp => self.scroll(0, -(height as isize / 2)),
+			KeyCode::PageDown => self.scroll(0, height as isize / 2),
+			KeyCode::Home => self.scroll(-(width as isize / 2), 0),
+			KeyCode::End => self.scroll(width as isize / 2, 0),
+			_ => (),
+		}
+		Ok(())
+	}
+
+	/// run a command typed after `:`
+	fn execute(&mut self, line: &str) -> io::Result<()> {
+		let action: Vec<_> = line.split_ascii_whitespace().collect();
+		if let Some(text) = line.strip_prefix("input ") {
+			self.interpreter.push_input(text.as_bytes());
+			return Ok(());
+		}
+		if let Some(arg) = line.strip_prefix("break char ") {
+			self.add_char_breakpoint(arg);
+			return Ok(());
+		}
+		if let Some(arg) = line.strip_prefix("break if ") {
+			if let Some(expr) = self.parse_expr(arg) {
+				self.breakpoints.push(Breakpoint::Condition(expr));
+			}
+			return Ok(());
+		}
+		if let Some(path) = line.strip_prefix("save ") {
+			self.message = Some(match fs::write(path, self.interpreter.save()) {
+				Ok(()) => format!("saved to {path}"),
+				Err(err) => format!("could not save: {err}").red().to_string(),
+			});
+			return Ok(());
+		}
+		if let Some(path) = line.strip_prefix("load ") {
+			self.load(path);
+			return Ok(());
+		}
+		if let Some(arg) = line.strip_prefix("watch ") {
+			if let Some(expr) = self.parse_expr(arg) {
+				self.watches.push(expr);
+			}
+			return Ok(());
+		}
+		match action.as_slice() {
+			[] | ["step"] => _ = self.step(1),
+			["step", num] => _ = num.parse().map(|n| self.step(n)),
+			["run"] => self.run_until_paused()?,
+			["play"] => self.play(PLAY_SPEED)?,
+			["play", num] => match num.parse::<f64>() {
+				Ok(speed) if speed > 0.0 && speed.is_finite() => self.play(speed)?,
+				_ => self.message = Some("usage: play [steps_per_second]".red().to_string()),
+			},
+			["back"] => self.back(1),
+			["back", num] => _ = num.parse().map(|n| self.back(n)),
+			["break", x, y] => match (x.parse(), y.parse()) {
+				(Ok(x), Ok(y)) => self.breakpoints.push(Breakpoint::Position(x, y)),
+				_ => self.message = Some("usage: break x y".red().to_string()),
+			},
+			["list"] => self.list(),
+			["delete"] => self.breakpoints.clear(),
+			["delete", num] => self.delete_breakpoint(num),
+			["unwatch"] => self.watches.clear(),
+			["unwatch", num] => self.delete_watch(num),
+			["cycles", "exact"] => self.set_cycle_detection(Some(CycleMode::Exact)),
+			["cycles", "heuristic"] => self.set_cycle_detection(Some(CycleMode::Heuristic)),
+			["cycles", "off"] => self.set_cycle_detection(None),
+			["q" | "exit" | "quit"] => self.quit = true,
+
+			_ => self.message = Some("unrecognised command".red().to_string()),
+		}
+		Ok(())
+	}
+
+	/// returns false if it stopped early, because of a breakpoint, an error or the end of the program
+	fn step(&mut self, n: usize) -> bool {
+		for _ in 0..n {
+			if self.interpreter.state() != State::Running {
+				return false;
+			}
+			if let Err(err) = self.interpreter.step_once() {
+				self.message = Some(err.to_string().red().to_string());
+				return false;
+			}
+			self.history.record(&self.interpreter);
+			self.write_trace();
+			self.log_watches();
+			let cycle = self
+				.cycles
+				.as_mut()
+				.and_then(|d| d.check(&self.interpreter));
+			if let Some(cycle) = cycle {
+				self.message = Some(cycle.to_string().red().to_string());
+				// keep going from here if asked, and report the loop again once it comes around
+				self.set_cycle_detection(Some(cycle.mode));
+				return false;
+			}
+			let hit = self
+				.breakpoints
+				.iter()
+				.position(|b| b.is_hit(&self.interpreter));
+			if let Some(index) = hit {
+				self.message = Some(format!(
+					"hit breakpoint {}: {}",
+					index + 1,
+					self.breakpoints[index]
+				));
+				return false;
+			}
+		}
+		true
+	}
+
+	/// keep stepping until the program stops or p is pressed, redrawing now and then
+	fn run_until_paused(&mut self) -> io::Result<()> {
+		let mut last_frame = Instant::now();
+		while self.step(RUN_BATCH) {
+			if last_frame.elapsed() >= RUN_FRAME_TIME {
+				self.message = Some("running, press p to pause".into());
+				self.show()?;
+				self.message = None;
+				last_frame = Instant::now();
+			}
+			if !event::poll(Duration::ZERO)? {
+				continue;
+			}
+			if let Event::Key(key) = event::read()? {
+				if is_ctrl_c(key) || key.code == KeyCode::Char('q') {
+					self.quit = true;
+					break;
+				}
+				if matches!(key.code, KeyCode::Char('p') | KeyCode::Esc) {
+					self.message = Some("paused".into());
+					break;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// step at a steady pace and redraw in between, until any key other than + or - is pressed
+	fn play(&mut self, mut speed: f64) -> io::Result<()> {
+		let mut start = Instant::now();
+		let mut taken = 0;
+		loop {
+			let due = (start.elapsed().as_secs_f64() * speed) as usize;
+			if due > taken {
+				if !self.step(due - taken) {
+					return Ok(());
+				}
+				taken = due;
+			}
+			self.message = Some(format!(
+				"playing at {speed} steps per second, + and - change the speed, any other key pauses"
+			));
+			self.show()?;
+			self.message = None;
+			let next_step = Duration::from_secs_f64((taken + 1) as f64 / speed);
+			let wait = next_step
+				.saturating_sub(start.elapsed())
+				.max(PLAY_FRAME_TIME);
+			if !event::poll(wait)? {
+				continue;
+			}
+			match event::read()? {
+				Event::Key(key) if key.kind == KeyEventKind::Release => continue,
+				Event::Key(key) => match key.code {
+					KeyCode::Char('+' | '=') => speed = (speed * 2.0).min(1e6),
+					KeyCode::Char('-') => speed = (speed / 2.0).max(0.25),
+					_ => {
+						self.message = Some("paused".into());
+						return Ok(());
+					}
+				},
+				_ => continue,
+			}
+			// count from the new speed
+			start = Instant::now();
+			taken = 0;
+		}
+	}
+
+	fn add_char_breakpoint(&mut self, arg: &str) {
+		let byte = match arg.as_bytes() {
+			[b'\'', byte, b'\''] | [byte] => *byte,
+			_ => {
+				self.message = Some("usage: break char 'c'".red().to_string());
+				return;
+			}
+		};
+		self.breakpoints.push(Breakpoint::Instruction(byte));
+	}
+
+	/// parse an expression, and check that it can be evaluated
+	fn parse_expr(&mut self, source: &str) -> Option<Expr> {
+		match Expr::parse(source).and_then(|expr| expr.eval(&self.interpreter).map(|_| expr)) {
+			Ok(expr) => Some(expr),
+			Err(err) => {
+				self.message = Some(format!("invalid expression: {err}").red().to_string());
+				None
+			}
+		}
+	}
+
+	fn write_trace(&mut self) {
+		let Some(trace) = &mut self.trace else {
+			return;
+		};
+		if let Err(err) = writeln!(trace, "{}", self.interpreter.last_step().to_json()) {
+			self.message = Some(format!("stopped tracing: {err}").red().to_string());
+			self.trace = None;
+		}
+	}
+
+	fn log_watches(&mut self) {
+		for watch in &self.watches {
+			let value = match watch.eval(&self.interpreter) {
+				Ok(value) => value.to_string(),
+				Err(err) => err.to_string(),
+			};
+			self.watch_log
+				.push(format!("[{}] {watch} = {value}", self.interpreter.steps()));
+		}
+		if self.watch_log.len() > WATCH_LOG_LINES * 2 {
+			self.watch_log
+				.drain(..self.watch_log.len() - WATCH_LOG_LINES);
+		}
+	}
+
+	fn list(&mut self) {
+		let breakpoints = self
+			.breakpoints
+			.iter()
+			.enumerate()
+			.map(|(i, breakpoint)| format!("break {}: {breakpoint}", i + 1));
+		let watches = self
+			.watches
+			.iter()
+			.enumerate()
+			.map(|(i, watch)| format!("watch {}: {watch}", i + 1));
+		let list: Vec<_> = breakpoints.chain(watches).collect();
+		self.message = Some(if list.is_empty() {
+			"no breakpoints or watches".into()
+		} else {
+			list.join("\n")
+		});
+	}
+
+	fn delete_breakpoint(&mut self, num: &str) {
+		match num.parse::<usize>() {
+			Ok(n) if (1..=self.breakpoints.len()).contains(&n) => {
+				self.breakpoints.remove(n - 1);
+			}
+			_ => self.message = Some(format!("no breakpoint {num}").red().to_string()),
+		}
+	}
+
+	fn delete_watch(&mut self, num: &str) {
+		match num.parse::<usize>() {
+			Ok(n) if (1..=self.watches.len()).contains(&n) => {
+				self.watches.remove(n - 1);
+			}
+			_ => self.message = Some(format!("no watch {num}").red().to_string()),
+		}
+	}
+
+	fn back(&mut self, n: usize) {
+		self.occupancy.clear();
+		match self.history.back(&mut self.interpreter, n) {
+			Ok(undone) if undone < n => {
+				self.message = Some(format!("went back {undone} turns, no earlier history"));
+			}
+			Ok(_) => (),
+			Err(err) => self.message = Some(err.to_string().red().to_string()),
+		}
+		self.set_cycle_detection(self.cycles.as_ref().map(CycleDetector::mode));
+	}
+
+	/// replace the interpreter with a saved one. history starts over from there
+	fn load(&mut self, path: &str) {
+		let loaded = fs::read_to_string(path)
+			.map_err(WormError::from)
+			.and_then(|text| SandWormInterpreter::load(&text));
+		match loaded {
+			Ok(interpreter) => {
+				self.interpreter = interpreter;
+				self.occupancy.clear();
+				self.set_cycle_detection(self.cycles.as_ref().map(CycleDetector::mode));
+				self.history = History::default();
+				self.history.record(&self.interpreter);
+				self.message = Some(format!("loaded {path}"));
+			}
+			Err(err) => {
+				self.message = Some(format!("could not load {path}: {err}").red().to_string())
+			}
+		}
+	}
+
+	/// move the view by hand, which stops it from following the worm
+	fn scroll(&mut self, dx: isize, dy: isize) {
+		self.follow = false;
+		self.view.0 = self.view.0.saturating_add_signed(dx);
+		self.view.1 = self.view.1.saturating_add_signed(dy);
+	}
+
+	/// the message lines that fit on screen
+	fn message_lines(&self, rows: usize) -> Vec<&str> {
+		let lines = self.message.as_deref().map(str::lines);
+		lines.into_iter().flatten().take(rows / 3).collect()
+	}
+
+	/// how many grid cells fit next to the panel and above the message
+	fn view_size(&self) -> io::Result<(usize, usize)> {
+		let (cols, rows) = terminal::size()?;
+		let (cols, rows) = (cols as usize, rows as usize);
+		let width = cols.saturating_sub(PANEL_WIDTH.min(cols / 2) + 1);
+		let height = rows.saturating_sub(self.message_lines(rows).len() + 1);
+		Ok((width, height))
+	}
+
+	/// scroll so that the head stays away from the edges of the view, and keep the view on the grid
+	fn update_view(&mut self, width: usize, height: usize) {
+		let (width, height) = (width.max(1), height.max(1));
+		if self.follow {
+			let (x, y) = self.interpreter.worm().head();
+			let margin_x = SCROLL_MARGIN.min(width.saturating_sub(1) / 2);
+			let margin_y = SCROLL_MARGIN.min(height.saturating_sub(1) / 2);
+			self.view.0 = self.view.0.clamp(
+				(x + margin_x + 1).saturating_sub(width),
+				x.saturating_sub(margin_x),
+			);
+			self.view.1 = self.view.1.clamp(
+				(y + margin_y + 1).saturating_sub(height),
+				y.saturating_sub(margin_y),
+			);
+		}
+		self.view.0 = self
+			.view
+			.0
+			.min(self.interpreter.width().saturating_sub(width));
+		self.view.1 = self
+			.view
+			.1
+			.min(self.interpreter.height().saturating_sub(height));
+	}
+
+	/// stacks, input, output and watches, cut to fit the side panel
+	fn panel(&self, width: usize, height: usize) -> Vec<String> {
+		let interpreter = &self.interpreter;
+		let state = match interpreter.state() {
+			State::Running => "running",
+			State::EndOfProgram => "ended",
+			State::Deadlocked => "deadlocked",
+			State::Halted(_) => "halted",
+		};
+		let (view_x, view_y) = interpreter.source_position(self.view);
+		let mut lines = vec![
+			format!("steps: {}  {state}", interpreter.steps()),
+			format!(
+				"view: {view_x} {view_y}{}",
+				if self.follow { " (following)" } else { "" }
+			),
+		];
+		let worms = interpreter.worms();
+		for (i, worm) in worms.iter().enumerate().filter(|(_, w)| w.is_alive()) {
+			let direction = match worm.direction() {
+				Direction::Up => '^',
+				Direction::Down => 'v',
+				Direction::Left => '<',
+				Direction::Right => '>',
+			};
+			let marker = if i == interpreter.current() { "*" } else { " " };
+			lines.push(format!(
+				"{marker}worm {i} {direction} len {}",
+				worm.values().len()
+			));
+			// the top of the stack is the interesting end
+			let stack = wrap(&format!("{:?}", worm.values()), width);
+			lines.extend(stack[stack.len().saturating_sub(3)..].iter().cloned());
+		}
+		match interpreter.state() {
+			State::Deadlocked => lines.extend(wrap("every worm is blocked by a body", width)),
+			State::Halted(code) => lines.push(format!("exit code {code}")),
+			_ => (),
+		}
+
+		let input = interpreter.input().escape_ascii().to_string();
+		let input = wrap(&input, width);
+		lines.push(format!("input ({} read):", interpreter.input_index()));
+		lines.extend(input[input.len().saturating_sub(2)..].iter().cloned());
+
+		let watches = &self.watch_log[self.watch_log.len().saturating_sub(5)..];
+		let watch_lines = if watches.is_empty() {
+			0
+		} else {
+			watches.len() + 1
+		};
+		lines.push("output:".into());
+		let output: Vec<_> = String::from_utf8_lossy(interpreter.output())
+			.split('\n')
+			.flat_map(|line| wrap(line, width))
+			.collect();
+		let room = height.saturating_sub(lines.len() + watch_lines);
+		lines.extend(output[output.len().saturating_sub(room)..].iter().cloned());
+		if !watches.is_empty() {
+			lines.push("watches:".into());
+			lines.extend(watches.iter().flat_map(|line| wrap(line, width)));
+		}
+		lines.truncate(height);
+		lines
+	}
+
+	/// draws everything into a buffer and writes it in one go, to avoid flickering
+	fn show(&mut self) -> io::Result<()> {
+		let (cols, rows) = terminal::size()?;
+		let (cols, rows) = (cols as usize, rows as usize);
+		if self
+			.interpreter
+			.worms()
+			.iter()
+			.any(|w| w.is_alive() && self.interpreter.get(w.head()) != Some(b'@'))
+		{
+			self.message = Some("worm head corrupted".red().to_string());
+		}
+		let (width, height) = self.view_size()?;
+		self.update_view(width, height);
+
+		// the whole frame goes out in one write, so the terminal never shows half of it
+		let mut out = Vec::new();
+		queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+		self.occupancy.update(&self.interpreter);
+		let view = Viewport {
+			x: self.view.0,
+			y: self.view.1,
+			width,
+			height,
+		};
+		draw_grid(&self.interpreter, &self.occupancy, view, &mut out)?;
+
+		let panel_width = cols.saturating_sub(width + 2);
+		for (row, line) in self.panel(panel_width, height).iter().enumerate() {
+			queue!(out, cursor::MoveTo(width as u16, row as u16))?;
+			write!(out, "{}{line}", "│".dimmed())?;
+		}
+		for (row, line) in self.message_lines(rows).iter().enumerate() {
+			queue!(out, cursor::MoveTo(0, (height + row) as u16))?;
+			write!(out, "{line}")?;
+		}
+		queue!(out, cursor::MoveTo(0, rows.saturating_sub(1) as u16))?;
+		if let Some(command) = &self.command {
+			write!(out, ":{command}")?;
+			queue!(out, cursor::Show)?;
+		} else {
+			let help: String = HELP.chars().take(cols).collect();
+			write!(out, "{}", help.dimmed())?;
+			queue!(out, cursor::Hide)?;
+		}
+		let mut stdout = stdout().lock();
+		stdout.write_all(&out)?;
+		stdout.flush()
+	}
+}
+
+fn is_ctrl_c(key: KeyEvent) -> bool {
+	key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// split text into lines of at most `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+	let chars: Vec<_> = text.chars().collect();
+	if chars.is_empty() || width == 0 {
+		return vec![String::new()];
+	}
+	chars
+		.chunks(width)
+		.map(|chunk| chunk.iter().collect())
+		.collect()
+}
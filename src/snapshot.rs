@@ -0,0 +1,336 @@
+//! the whole interpreter state as text, so it can be saved and picked up again later.
+//!
+//! version 1 of the format has one field per line, in this order:
+//! ```text
+//! worm snapshot 1
+//! size WIDTH HEIGHT
+//! origin X Y
+//! topology bounded|torus|unbounded
+//! state running|ended|deadlocked|halted CODE
+//! steps N
+//! current N           index of the worm that moves next
+//! blocked N           turns in a row where a worm was blocked
+//! input_index N
+//! input "BYTES"       all input read or provided so far
+//! output "BYTES"
+//! worms N
+//! ```
+//! then for each worm, in the order they take turns:
+//! ```text
+//! worm X Y up|down|left|right alive|dead     the head
+//! body X,Y X,Y ...                           from the tail to the neck
+//! in "BYTES"
+//! out "BYTES"                                the next instruction to leave the tail is last
+//! ```
+//! and finally `grid` followed by HEIGHT lines of "BYTES", the grid with the worm bodies filled in.
+//!
+//! "BYTES" are quoted, with `\\`, `\"`, `\n`, `\t`, `\r`, `\'` and `\xNN` escapes.
+//! any input source that was still being read from is not saved, only the bytes read so far
+//! and any added after them
+
+use std::{
+	collections::VecDeque,
+	fmt::Write,
+	sync::{Arc, Mutex},
+};
+
+use crate::{
+	lock_input, Direction, Input, SandWormInterpreter, State, StepInfo, Topology, Worm, WormError,
+};
+
+const HEADER: &str = "worm snapshot 1";
+
+impl SandWormInterpreter {
+	pub fn save(&self) -> String {
+		let mut out = String::new();
+		_ = writeln!(out, "{HEADER}");
+		_ = writeln!(out, "size {} {}", self.width, self.height);
+		_ = writeln!(out, "origin {} {}", self.origin.0, self.origin.1);
+		let topology = match self.topology {
+			Topology::Bounded => "bounded",
+			Topology::Torus => "torus",
+			Topology::Unbounded => "unbounded",
+		};
+		_ = writeln!(out, "topology {topology}");
+		let state = match self.state {
+			State::Running => "running",
+			State::EndOfProgram => "ended",
+			State::Deadlocked => "deadlocked",
+			State::Halted(code) => &format!("halted {code}"),
+		};
+		_ = writeln!(out, "state {state}");
+		_ = writeln!(out, "steps {}", self.steps);
+		_ = writeln!(out, "current {}", self.current);
+		_ = writeln!(out, "blocked {}", self.blocked_turns);
+		_ = writeln!(out, "input_index {}", self.input_index);
+		// input typed while a source was still being read comes after the bytes read from it
+		let input = lock_input(&self.input);
+		let input = [input.buffered(), input.queued()].concat();
+		_ = writeln!(out, "input \"{}\"", input.escape_ascii());
+		_ = writeln!(out, "output \"{}\"", self.output.escape_ascii());
+		_ = writeln!(out, "worms {}", self.worms.len());
+		for worm in &self.worms {
+			let direction = match worm.direction {
+				Direction::Up => "up",
+				Direction::Down => "down",
+				Direction::Left => "left",
+				Direction::Right => "right",
+			};
+			let alive = if worm.alive { "alive" } else { "dead" };
+			let (x, y) = worm.head;
+			_ = writeln!(out, "worm {x} {y} {direction} {alive}");
+			let body: Vec<_> = worm
+				.body()
+				.iter()
+				.map(|(x, y)| format!("{x},{y}"))
+				.collect();
+			_ = writeln!(out, "body {}", body.join(" "));
+			_ = writeln!(out, "in \"{}\"", worm.worm_in.escape_ascii());
+			let worm_out: Vec<_> = worm.worm_out.iter().copied().collect();
+			_ = writeln!(out, "out \"{}\"", worm_out.escape_ascii());
+		}
+		_ = writeln!(out, "grid");
+		for row in self.program() {
+			_ = writeln!(out, "\"{}\"", row.escape_ascii());
+		}
+		out
+	}
+
+	/// read a snapshot made by `save`
+	pub fn load(text: &str) -> Result<Self, WormError> {
+		let mut reader = Reader {
+			lines: text.lines(),
+			line: 0,
+		};
+		if reader.next()? != HEADER {
+			return Err(reader.error("not a version 1 worm snapshot"));
+		}
+		let [width, height] = reader.numbers("size")?;
+		let [origin_x, origin_y] = reader.numbers("origin")?;
+		let topology = match reader.field("topology")? {
+			"bounded" => Topology::Bounded,
+			"torus" => Topology::Torus,
+			"unbounded" => Topology::Unbounded,
+			other => return Err(reader.error(format!("unknown topology '{other}'"))),
+		};
+		let state = match reader.field("state")? {
+			"running" => State::Running,
+			"ended" => State::EndOfProgram,
+			"deadlocked" => State::Deadlocked,
+			other => match other.strip_prefix("halted ").map(str::parse) {
+				Some(Ok(code)) => State::Halted(code),
+				_ => return Err(reader.error(format!("unknown state '{other}'"))),
+			},
+		};
+		let [steps] = reader.numbers("steps")?;
+		let [current] = reader.numbers("current")?;
+		let [blocked_turns] = reader.numbers("blocked")?;
+		let [input_index] = reader.numbers("input_index")?;
+		let input = reader.bytes("input")?;
+		let output = reader.bytes("output")?;
+		let [worm_count] = reader.numbers("worms")?;
+		if current >= worm_count {
+			return Err(reader.error("current worm does not exist"));
+		}
+
+		let in_grid = |(x, y): (usize, usize)| x < width && y < height;
+		if !in_grid((origin_x, origin_y)) {
+			return Err(reader.error("origin is outside the grid"));
+		}
+		let mut worms = Vec::new();
+		for _ in 0..worm_count {
+			let head = reader.field("worm")?;
+			let &[x, y, direction, alive] = head.split(' ').collect::<Vec<_>>().as_slice() else {
+				return Err(reader.error("expected 'worm X Y DIRECTION alive|dead'"));
+			};
+			let head = (reader.number(x)?, reader.number(y)?);
+			let direction = match direction {
+				"up" => Direction::Up,
+				"down" => Direction::Down,
+				"left" => Direction::Left,
+				"right" => Direction::Right,
+				other => return Err(reader.error(format!("unknown direction '{other}'"))),
+			};
+			let alive = match alive {
+				"alive" => true,
+				"dead" => false,
+				other => return Err(reader.error(format!("expected alive or dead, not '{other}'"))),
+			};
+			if !in_grid(head) {
+				return Err(reader.error("worm head is outside the grid"));
+			}
+			let mut body = Vec::new();
+			for pos in reader.field("body")?.split(' ').filter(|s| !s.is_empty()) {
+				let Some((x, y)) = pos.split_once(',') else {
+					return Err(reader.error(format!("expected X,Y, not '{pos}'")));
+				};
+				let pos = (reader.number(x)?, reader.number(y)?);
+				// a worm that ran into its own body can have segments on the same cell
+				if !in_grid(pos) {
+					return Err(reader.error(format!("body segment {pos:?} is outside the grid")));
+				}
+				body.push(pos);
+			}
+			// worms that have left are just cells now, but living ones can't share a cell
+			let taken = |pos| {
+				worms.iter().any(|w: &Worm| {
+					w.alive && alive && (w.head == pos || w.segments.contains_key(&pos))
+				})
+			};
+			if taken(head) || body.iter().any(|&pos| taken(pos)) {
+				return Err(reader.error("worm overlaps another worm"));
+			}
+			let worm_in = reader.bytes("in")?;
+			let worm_out = reader.bytes("out")?;
+			worms.push(Worm {
+				segments: (body.iter().copied()).zip(0..).collect(),
+				body,
+				head,
+				worm_in,
+				worm_out: VecDeque::from(worm_out),
+				direction,
+				alive,
+				..Worm::default()
+			});
+		}
+
+		if reader.next()? != "grid" {
+			return Err(reader.error("expected grid"));
+		}
+		let mut program = Vec::new();
+		for _ in 0..height {
+			let line = reader.next()?;
+			let row = reader.unquote(line)?;
+			if row.len() != width {
+				return Err(reader.error(format!("row is {} bytes, not {width}", row.len())));
+			}
+			program.push(row);
+		}
+		if width == 0 || height == 0 {
+			return Err(reader.error("the grid is empty"));
+		}
+		if state == State::Running && !worms[current].alive {
+			return Err(
+				reader.error("the current worm has left the grid, but the program is running")
+			);
+		}
+		for worm in &mut worms {
+			worm.values = worm.body.iter().map(|&(x, y)| program[y][x]).collect();
+			if !worm.alive {
+				worm.segments.clear();
+				continue;
+			}
+			// a worm on top of itself keeps its values on the grid
+			worm.tangled =
+				worm.segments.len() != worm.body.len() || worm.segments.contains_key(&worm.head);
+			if !worm.tangled {
+				// the same as running leaves it, the head went over every body cell
+				for &(x, y) in &worm.body {
+					program[y][x] = b'@';
+				}
+			}
+		}
+
+		Ok(Self {
+			program,
+			width,
+			height,
+			origin: (origin_x, origin_y),
+			worms,
+			current,
+			blocked_turns,
+			topology,
+			input: Arc::new(Mutex::new(Input::new(input))),
+			input_index,
+			output,
+			state,
+			steps,
+			turns: 0,
+			last_step: StepInfo::default(),
+		})
+	}
+}
+
+struct Reader<'a> {
+	lines: std::str::Lines<'a>,
+	/// the line last read, counting from 1
+	line: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn error(&self, message: impl Into<String>) -> WormError {
+		WormError::Snapshot {
+			line: self.line,
+			message: message.into(),
+		}
+	}
+
+	fn next(&mut self) -> Result<&'a str, WormError> {
+		self.line += 1;
+		let line = self.lines.next();
+		line.ok_or_else(|| self.error("unexpected end of snapshot"))
+	}
+
+	/// the rest of a line starting with `name`
+	fn field(&mut self, name: &str) -> Result<&'a str, WormError> {
+		let line = self.next()?;
+		match line.strip_prefix(name) {
+			Some("") => Ok(""),
+			Some(rest) if rest.starts_with(' ') => Ok(&rest[1..]),
+			_ => Err(self.error(format!("expected {name}"))),
+		}
+	}
+
+	fn number(&self, text: &str) -> Result<usize, WormError> {
+		text.parse()
+			.map_err(|_| self.error(format!("expected a number, not '{text}'")))
+	}
+
+	fn numbers<const N: usize>(&mut self, name: &str) -> Result<[usize; N], WormError> {
+		let fields: Vec<_> = self.field(name)?.split(' ').collect();
+		if fields.len() != N {
+			return Err(self.error(format!("expected {N} numbers after {name}")));
+		}
+		let mut numbers = [0; N];
+		for (number, text) in numbers.iter_mut().zip(fields) {
+			*number = self.number(text)?;
+		}
+		Ok(numbers)
+	}
+
+	fn bytes(&mut self, name: &str) -> Result<Vec<u8>, WormError> {
+		let text = self.field(name)?;
+		self.unquote(text)
+	}
+
+	/// undo `escape_ascii` on a quoted string
+	fn unquote(&self, text: &str) -> Result<Vec<u8>, WormError> {
+		let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) else {
+			return Err(self.error("expected a quoted string"));
+		};
+		let mut bytes = Vec::new();
+		let mut chars = inner.bytes();
+		while let Some(byte) = chars.next() {
+			if byte != b'\\' {
+				bytes.push(byte);
+				continue;
+			}
+			bytes.push(match chars.next() {
+				Some(b'n') => b'\n',
+				Some(b't') => b'\t',
+				Some(b'r') => b'\r',
+				Some(b'x') => {
+					let hex = [chars.next(), chars.next()];
+					let hex = hex.map(|c| (c.unwrap_or_default() as char).to_digit(16));
+					match hex {
+						[Some(high), Some(low)] => (high * 16 + low) as u8,
+						_ => return Err(self.error("invalid \\x escape")),
+					}
+				}
+				Some(byte @ (b'\\' | b'"' | b'\'')) => byte,
+				_ => return Err(self.error("invalid escape")),
+			});
+		}
+		Ok(bytes)
+	}
+}
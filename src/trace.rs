@@ -0,0 +1,90 @@
+use std::fmt::Write;
+
+use crate::Direction;
+
+/// what happened in the last call to `step_once`, for tracing
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StepInfo {
+	/// steps taken before this one
+	pub step: usize,
+	/// the worm whose turn it was
+	pub worm: usize,
+	pub turn: Turn,
+	/// where the head is afterwards, counted from the top left of the source
+	pub head: (i64, i64),
+	pub direction: Direction,
+	/// `None` if the worm didn't move
+	pub instruction: Option<u8>,
+	/// body length afterwards
+	pub length: usize,
+	/// values taken off the stack, the top first
+	pub popped: Vec<u8>,
+	pub pushed: Vec<u8>,
+	pub output: Vec<u8>,
+	pub input: Vec<u8>,
+}
+
+/// what happened on a worm's turn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
+	#[default]
+	Moved,
+	/// a worm, possibly this one, is in the way
+	Blocked,
+	LeftGrid,
+}
+
+impl StepInfo {
+	/// one line of JSON, without the newline.
+	/// the instruction is a string with bytes above 127 as `\u0080` to `\u00ff`, other bytes are numbers
+	pub fn to_json(&self) -> String {
+		let turn = match self.turn {
+			Turn::Moved => "moved",
+			Turn::Blocked => "blocked",
+			Turn::LeftGrid => "left_grid",
+		};
+		let direction = match self.direction {
+			Direction::Up => "up",
+			Direction::Down => "down",
+			Direction::Left => "left",
+			Direction::Right => "right",
+		};
+		let instruction = match self.instruction {
+			Some(byte) => json_string(byte),
+			None => "null".into(),
+		};
+		format!(
+			r#"{{"step":{},"worm":{},"turn":"{turn}","x":{},"y":{},"direction":"{direction}","instruction":{instruction},"length":{},"popped":{},"pushed":{},"output":{},"input":{}}}"#,
+			self.step,
+			self.worm,
+			self.head.0,
+			self.head.1,
+			self.length,
+			json_array(&self.popped),
+			json_array(&self.pushed),
+			json_array(&self.output),
+			json_array(&self.input),
+		)
+	}
+}
+
+fn json_string(byte: u8) -> String {
+	match byte {
+		b'"' => r#""\"""#.into(),
+		b'\\' => r#""\\""#.into(),
+		b' '..=b'~' => format!("\"{}\"", byte as char),
+		_ => format!("\"\\u{byte:04x}\""),
+	}
+}
+
+fn json_array(bytes: &[u8]) -> String {
+	let mut out = String::from("[");
+	for (i, byte) in bytes.iter().enumerate() {
+		if i > 0 {
+			out.push(',');
+		}
+		_ = write!(out, "{byte}");
+	}
+	out.push(']');
+	out
+}
@@ -0,0 +1,76 @@
+use worm::{Breakpoint, Expr, SandWormInterpreter};
+
+/// step until `breakpoint` is hit, returning how many steps that took
+fn run_to(interpreter: &mut SandWormInterpreter, breakpoint: &Breakpoint) -> Option<usize> {
+	for _ in 0..1000 {
+		if breakpoint.is_hit(interpreter) {
+			return Some(interpreter.steps());
+		}
+		interpreter.step_once().unwrap();
+	}
+	None
+}
+
+#[test]
+fn position_is_hit_when_a_head_arrives() {
+	let mut worm = SandWormInterpreter::new("@  v\n   >  ", Vec::new()).unwrap();
+	let breakpoint = Breakpoint::Position(3, 1);
+	assert_eq!(run_to(&mut worm, &breakpoint), Some(4));
+	assert_eq!(worm.worm().head(), (3, 1));
+	worm.step_once().unwrap();
+	assert!(!breakpoint.is_hit(&worm));
+}
+
+#[test]
+fn position_is_hit_by_any_worm() {
+	let mut worm = SandWormInterpreter::new("@    \n@    ", Vec::new()).unwrap();
+	let breakpoint = Breakpoint::Position(2, 1);
+	assert_eq!(run_to(&mut worm, &breakpoint), Some(4));
+	assert_eq!(worm.worms()[1].head(), (2, 1));
+}
+
+#[test]
+fn position_ignores_worms_that_left() {
+	// the body left behind at 0 0 is an ordinary cell, and the head is gone
+	let mut worm = SandWormInterpreter::new("@1", Vec::new()).unwrap();
+	worm.run().unwrap();
+	assert!(!Breakpoint::Position(1, 0).is_hit(&worm));
+}
+
+#[test]
+fn instruction_is_hit_before_it_runs() {
+	let mut worm = SandWormInterpreter::new("@99+!  ", Vec::new()).unwrap();
+	let breakpoint = Breakpoint::Instruction(b'!');
+	assert_eq!(run_to(&mut worm, &breakpoint), Some(3));
+	assert_eq!(worm.next_instruction(), Some(b'!'));
+	assert!(worm.output().is_empty());
+	worm.step_once().unwrap();
+	assert_eq!(worm.output(), [18]);
+	assert!(!breakpoint.is_hit(&worm));
+}
+
+#[test]
+fn instruction_is_only_hit_for_the_worm_moving_next() {
+	let mut worm = SandWormInterpreter::new("@ \n@!", Vec::new()).unwrap();
+	let breakpoint = Breakpoint::Instruction(b'!');
+	assert!(!breakpoint.is_hit(&worm));
+	worm.step_once().unwrap();
+	assert!(breakpoint.is_hit(&worm));
+}
+
+#[test]
+fn condition_errors_count_as_false() {
+	let mut worm = SandWormInterpreter::new("@123    ", Vec::new()).unwrap();
+	let breakpoint = Breakpoint::Condition(Expr::parse("len >= 2").unwrap());
+	assert_eq!(run_to(&mut worm, &breakpoint), Some(2));
+	let broken = Breakpoint::Condition(Expr::parse("output < 1").unwrap());
+	assert!(!broken.is_hit(&worm));
+}
+
+#[test]
+fn display() {
+	assert_eq!(Breakpoint::Position(3, -1).to_string(), "3 -1");
+	assert_eq!(Breakpoint::Instruction(b'!').to_string(), "char '!'");
+	let condition = Breakpoint::Condition(Expr::parse("top == 3").unwrap());
+	assert_eq!(condition.to_string(), "if top == 3");
+}
@@ -0,0 +1,118 @@
+use std::fs;
+
+use worm::{Cycle, CycleDetector, CycleMode, SandWormInterpreter, State, Topology};
+
+/// run until a cycle is found or the program stops
+fn find_cycle(interpreter: &mut SandWormInterpreter, mode: CycleMode) -> Option<Cycle> {
+	let mut detector = CycleDetector::new(mode);
+	assert_eq!(detector.check(interpreter), None);
+	while interpreter.state() == State::Running {
+		interpreter.step_once().unwrap();
+		if let Some(cycle) = detector.check(interpreter) {
+			return Some(cycle);
+		}
+	}
+	None
+}
+
+fn program(name: &str) -> String {
+	fs::read_to_string(format!("programs/{name}.worm")).unwrap()
+}
+
+#[test]
+fn finds_double_loop() {
+	let source = program("double_loop");
+	let mut exact = SandWormInterpreter::new(&source, Vec::new()).unwrap();
+	let cycle = find_cycle(&mut exact, CycleMode::Exact).unwrap();
+	assert_eq!(cycle.length, 1152);
+	assert_eq!(cycle.start, 216);
+
+	let mut heuristic = SandWormInterpreter::new(&source, Vec::new()).unwrap();
+	let found = find_cycle(&mut heuristic, CycleMode::Heuristic).unwrap();
+	assert_eq!(found.length, cycle.length);
+	assert!(found.start >= cycle.start);
+}
+
+#[test]
+fn programs_that_end_have_no_cycle() {
+	for name in ["hello_world", "99_bottles_of_beer"] {
+		for mode in [CycleMode::Exact, CycleMode::Heuristic] {
+			let mut worm = SandWormInterpreter::new(&program(name), Vec::new()).unwrap();
+			assert_eq!(find_cycle(&mut worm, mode), None, "{name}");
+			assert_eq!(worm.state(), State::EndOfProgram);
+		}
+	}
+}
+
+#[test]
+fn output_does_not_hide_a_loop() {
+	let mut worm = SandWormInterpreter::new("@1\"", Vec::new()).unwrap();
+	worm.set_topology(Topology::Torus);
+	let cycle = find_cycle(&mut worm, CycleMode::Exact).unwrap();
+	assert_eq!(cycle.length, 6);
+	assert_eq!(worm.output(), b"111");
+}
+
+#[test]
+fn reading_past_the_input_loops() {
+	let mut worm = SandWormInterpreter::new("@?!", b"abc".to_vec()).unwrap();
+	worm.set_topology(Topology::Torus);
+	let cycle = find_cycle(&mut worm, CycleMode::Exact).unwrap();
+	assert_eq!(cycle.length, 6);
+	assert!(worm.output().starts_with(b"abc"));
+}
+
+#[test]
+fn reset_forgets_states() {
+	let mut worm = SandWormInterpreter::new("@_!", Vec::new()).unwrap();
+	worm.set_topology(Topology::Torus);
+	let mut detector = CycleDetector::new(CycleMode::Exact);
+	detector.check(&worm);
+	worm.step(6).unwrap();
+	detector.reset();
+	assert_eq!(detector.check(&worm), None);
+	worm.step(6).unwrap();
+	assert!(detector.check(&worm).is_some());
+}
+
+#[test]
+fn loaded_snapshots_hash_the_same() {
+	let mut worm = SandWormInterpreter::new(&program("double_loop"), Vec::new()).unwrap();
+	for _ in 0..20 {
+		worm.step(97).unwrap();
+		let loaded = SandWormInterpreter::load(&worm.save()).unwrap();
+		assert_eq!(loaded.state_hash(), worm.state_hash());
+	}
+}
+
+#[test]
+fn worms_that_have_left_hash_the_same_after_loading() {
+	const INSTRUCTIONS: &[u8] = b"0123456789+-*$#`v^<>\"!=~\\/ @@   ";
+	let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+	let mut random = move |n: usize| {
+		seed ^= seed << 13;
+		seed ^= seed >> 7;
+		seed ^= seed << 17;
+		(seed % n as u64) as usize
+	};
+	for program in 0..300 {
+		let (width, height) = (2 + random(12), 1 + random(6));
+		let source: Vec<String> = (0..height)
+			.map(|_| {
+				(0..width)
+					.map(|_| INSTRUCTIONS[random(INSTRUCTIONS.len())] as char)
+					.collect()
+			})
+			.collect();
+		let source = format!("@{}", source.join("\n"));
+		let mut worm = SandWormInterpreter::new(&source, Vec::new()).unwrap();
+		if program % 2 == 0 {
+			worm.set_topology(Topology::Torus);
+		}
+		for _ in 0..20 {
+			worm.step(5).unwrap();
+			let loaded = SandWormInterpreter::load(&worm.save()).unwrap();
+			assert_eq!(loaded.state_hash(), worm.state_hash(), "{source}");
+		}
+	}
+}
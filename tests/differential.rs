@@ -0,0 +1,305 @@
+//! runs the interpreter side by side with the original, simpler algorithm that
+//! moves the worm by shifting every body segment, and checks that they agree on every step
+
+use std::fs;
+
+use worm::{Direction, SandWormInterpreter, State, Topology};
+
+type Pos = (usize, usize);
+
+struct Reference {
+	program: Vec<Vec<u8>>,
+	width: usize,
+	height: usize,
+	worm: Vec<Pos>,
+	worm_head: Pos,
+	worm_out: Vec<u8>,
+	worm_in: Vec<u8>,
+	direction: Direction,
+	wrap: bool,
+	input: Vec<u8>,
+	input_index: usize,
+	output: Vec<u8>,
+	state: State,
+	steps: usize,
+}
+
+impl Reference {
+	fn new(source: &str, input: &[u8], wrap: bool) -> Self {
+		let mut program: Vec<Vec<u8>> = source.lines().map(|l| l.as_bytes().to_vec()).collect();
+		let width = program.iter().map(Vec::len).max().unwrap();
+		for line in &mut program {
+			line.resize(width, 0);
+		}
+		let row = program.iter().position(|l| l.contains(&b'@')).unwrap();
+		let col = program[row].iter().position(|&b| b == b'@').unwrap();
+		Self {
+			height: program.len(),
+			width,
+			program,
+			worm: Vec::new(),
+			worm_head: (col, row),
+			worm_out: Vec::new(),
+			worm_in: Vec::new(),
+			direction: Direction::Right,
+			wrap,
+			input: input.to_vec(),
+			input_index: 0,
+			output: Vec::new(),
+			state: State::Running,
+			steps: 0,
+		}
+	}
+
+	fn step_once(&mut self) {
+		let (x, y) = self.worm_head;
+		let front = match (self.direction, self.wrap) {
+			(Direction::Up, false) => (x, y.wrapping_sub(1)),
+			(Direction::Down, false) => (x, y + 1),
+			(Direction::Left, false) => (x.wrapping_sub(1), y),
+			(Direction::Right, false) => (x + 1, y),
+			(Direction::Up, true) => (x, (y + self.height - 1) % self.height),
+			(Direction::Down, true) => (x, (y + 1) % self.height),
+			(Direction::Left, true) => ((x + self.width - 1) % self.width, y),
+			(Direction::Right, true) => ((x + 1) % self.width, y),
+		};
+		if front.0 >= self.width || front.1 >= self.height {
+			self.state = State::EndOfProgram;
+			return;
+		}
+		self.steps += 1;
+		let instruction = self.program[front.1][front.0];
+		let mut dont_push_instruction = false;
+		match instruction {
+			b'0'..=b'9' => self.worm_in.push(instruction - 48),
+			b'+' | b'-' | b'*' | b';' | b'%' => {
+				let a = self.shrink();
+				self.worm_out.insert(0, instruction);
+				let b = self.shrink();
+				dont_push_instruction = true;
+				self.worm_in.push(match instruction {
+					b'+' => a.wrapping_add(b),
+					b'-' => a.wrapping_sub(b),
+					b'*' => a.wrapping_mul(b),
+					b';' => a.checked_div(b).unwrap_or(0),
+					_ => a.checked_rem(b).unwrap_or(0),
+				});
+			}
+			b'v' => self.direction = Direction::Down,
+			b'^' => self.direction = Direction::Up,
+			b'<' => self.direction = Direction::Left,
+			b'>' => self.direction = Direction::Right,
+			b'"' => {
+				let n = self.shrink();
+				self.output.extend(n.to_string().as_bytes());
+			}
+			b'!' => {
+				let n = self.shrink();
+				self.output.push(n);
+			}
+			b'?' => {
+				let val = self
+					.input
+					.get(self.input_index)
+					.copied()
+					.unwrap_or_default();
+				self.input_index += 1;
+				self.worm_in.push(val);
+			}
+			b'=' => {
+				let last_val = self.worm.last().map(|&p| self.get(p)).unwrap_or_default();
+				self.worm_in.push(last_val);
+			}
+			b'$' => {
+				self.shrink();
+			}
+			b'#' if self.worm.len() >= 2 => {
+				let len = self.worm.len();
+				let (top, below) = (self.worm[len - 1], self.worm[len - 2]);
+				let (a, b) = (self.get(top), self.get(below));
+				self.program[top.1][top.0] = b;
+				self.program[below.1][below.0] = a;
+			}
+			b'`' if self.worm.len() >= 3 => {
+				let len = self.worm.len();
+				let cells = &self.worm[len - 3..];
+				let mut values: Vec<u8> = cells.iter().map(|&p| self.get(p)).collect();
+				values.rotate_left(1);
+				for (&(x, y), value) in cells.iter().zip(values) {
+					self.program[y][x] = value;
+				}
+			}
+			b'#' | b'`' => (),
+			b'[' => {
+				let y = self.shrink() as usize;
+				let x = self.shrink() as usize;
+				let in_grid = x < self.width && y < self.height;
+				self.worm_in
+					.push(if in_grid { self.get((x, y)) } else { 0 });
+			}
+			b']' => {
+				let y = self.shrink() as usize;
+				let x = self.shrink() as usize;
+				let value = self.shrink();
+				if x < self.width && y < self.height && (x, y) != self.worm_head {
+					self.program[y][x] = value;
+				}
+			}
+			b'~' => {
+				let last_val = self.shrink();
+				self.worm_in.push((last_val == 0) as u8);
+			}
+			b'\\' | b'/' => {
+				let val = self.shrink();
+				if val != 0 {
+					self.direction = match (instruction, self.direction) {
+						(b'\\', Direction::Up) | (b'/', Direction::Down) => Direction::Left,
+						(b'\\', Direction::Down) | (b'/', Direction::Up) => Direction::Right,
+						(b'\\', Direction::Left) | (b'/', Direction::Right) => Direction::Up,
+						_ => Direction::Down,
+					}
+				}
+			}
+			b' ' | 0 => dont_push_instruction = true,
+			b'_' => self.worm_in.push(b' '),
+			other => self.worm_in.push(other),
+		}
+		if !dont_push_instruction {
+			self.worm_out.insert(0, instruction);
+		}
+		self.move_to(front);
+	}
+
+	fn move_to(&mut self, front: Pos) {
+		if let Some(input) = self.worm_in.pop() {
+			self.program[self.worm_head.1][self.worm_head.0] = input;
+			self.worm.push(self.worm_head);
+		} else {
+			let mut next = self.worm_head;
+			for body_segment in self.worm.iter_mut().rev() {
+				self.program[next.1][next.0] = self.program[body_segment.1][body_segment.0];
+				(*body_segment, next) = (next, *body_segment);
+			}
+			self.program[next.1][next.0] = self.worm_out.pop().unwrap_or(b' ');
+		}
+		self.worm_head = front;
+		self.program[front.1][front.0] = b'@';
+	}
+
+	fn shrink(&mut self) -> u8 {
+		let Some(neck) = self.worm.pop() else {
+			return 0;
+		};
+		let ret = self.get(neck);
+		let mut next = neck;
+		for body_segment in self.worm.iter_mut().rev() {
+			self.program[next.1][next.0] = self.program[body_segment.1][body_segment.0];
+			(*body_segment, next) = (next, *body_segment);
+		}
+		self.program[next.1][next.0] = self.worm_out.pop().unwrap_or(b' ');
+		ret
+	}
+
+	fn get(&self, pos: Pos) -> u8 {
+		self.program[pos.1][pos.0]
+	}
+}
+
+fn assert_same(reference: &Reference, interpreter: &SandWormInterpreter, context: &str) {
+	let worm = interpreter.worm();
+	assert_eq!(interpreter.program(), reference.program, "grid, {context}");
+	assert_eq!(worm.body(), reference.worm, "body, {context}");
+	let values: Vec<u8> = reference
+		.worm
+		.iter()
+		.map(|&pos| reference.get(pos))
+		.collect();
+	assert_eq!(worm.values(), values, "values, {context}");
+	// a step only changes the segments near the ends, so that's where the lookups can go wrong
+	let body = worm.body();
+	let ends = body.iter().take(8).chain(body.iter().rev().take(8));
+	for &pos in ends {
+		assert_eq!(
+			interpreter.get(pos),
+			Some(reference.get(pos)),
+			"{pos:?}, {context}"
+		);
+	}
+	assert_eq!(worm.head(), reference.worm_head, "head, {context}");
+	assert_eq!(worm.worm_in(), reference.worm_in, "worm_in, {context}");
+	assert_eq!(
+		worm.worm_out().iter().copied().collect::<Vec<_>>(),
+		reference.worm_out,
+		"worm_out, {context}"
+	);
+	assert_eq!(
+		worm.direction(),
+		reference.direction,
+		"direction, {context}"
+	);
+	assert_eq!(interpreter.output(), reference.output, "output, {context}");
+	assert_eq!(interpreter.state(), reference.state, "state, {context}");
+	assert_eq!(interpreter.steps(), reference.steps, "steps, {context}");
+}
+
+fn compare(name: &str, source: &str, input: &[u8], wrap: bool, max_steps: usize) {
+	let mut reference = Reference::new(source, input, wrap);
+	let mut interpreter = SandWormInterpreter::new(source, input.to_vec()).unwrap();
+	if wrap {
+		interpreter.set_topology(Topology::Torus);
+	}
+	assert_same(&reference, &interpreter, &format!("{name} at the start"));
+	for step in 1..=max_steps {
+		reference.step_once();
+		let result = interpreter.step_once();
+		assert!(result.is_ok(), "{name} step {step}: {result:?}");
+		assert_same(&reference, &interpreter, &format!("{name} step {step}"));
+		if reference.state != State::Running {
+			return;
+		}
+	}
+}
+
+#[test]
+fn example_programs() {
+	for entry in fs::read_dir("programs").unwrap() {
+		let path = entry.unwrap().path();
+		if path.extension().is_some_and(|ext| ext == "worm") {
+			let source = fs::read_to_string(&path).unwrap();
+			let name = path.display().to_string();
+			compare(&name, &source, b"some input\n", false, 30_000);
+			compare(&name, &source, b"some input\n", true, 3_000);
+		}
+	}
+}
+
+#[test]
+fn random_programs() {
+	const INSTRUCTIONS: &[u8] = b"0123456789+-*;%$#`[]v^<>\"!?=~\\/ _ab   ";
+	let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+	let mut random = move |n: usize| {
+		seed ^= seed << 13;
+		seed ^= seed >> 7;
+		seed ^= seed << 17;
+		(seed % n as u64) as usize
+	};
+	for program in 0..300 {
+		let width = 2 + random(30);
+		let height = 1 + random(12);
+		let mut grid: Vec<Vec<u8>> = (0..height)
+			.map(|_| {
+				(0..width)
+					.map(|_| INSTRUCTIONS[random(INSTRUCTIONS.len())])
+					.collect()
+			})
+			.collect();
+		grid[random(height)][random(width)] = b'@';
+		let source: Vec<_> = grid
+			.iter()
+			.map(|line| String::from_utf8(line.clone()).unwrap())
+			.collect();
+		let source = source.join("\n");
+		let name = format!("random program {program}:\n{source}\n");
+		compare(&name, &source, b"\x05abc", program % 2 == 0, 2_000);
+	}
+}
@@ -0,0 +1,117 @@
+use worm::{Expr, SandWormInterpreter, Value};
+
+fn eval(source: &str, interpreter: &SandWormInterpreter) -> Result<Value, String> {
+	let expr = Expr::parse(source).map_err(|err| err.to_string())?;
+	expr.eval(interpreter).map_err(|err| err.to_string())
+}
+
+/// evaluate on a program that hasn't started
+fn value(source: &str) -> Value {
+	let interpreter = SandWormInterpreter::new("@ ", Vec::new()).unwrap();
+	eval(source, &interpreter).unwrap()
+}
+
+fn error(source: &str) -> String {
+	let interpreter = SandWormInterpreter::new("@ ", Vec::new()).unwrap();
+	eval(source, &interpreter).unwrap_err()
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+	assert_eq!(value("1 || 0 && 0"), Value::Int(1));
+	assert_eq!(value("(1 || 0) && 0"), Value::Int(0));
+	assert_eq!(value("0 && 1 || 1"), Value::Int(1));
+	assert_eq!(value("1 < 2 && 3 >= 3"), Value::Int(1));
+}
+
+#[test]
+fn not() {
+	assert_eq!(value("!0"), Value::Int(1));
+	assert_eq!(value("!5"), Value::Int(0));
+	assert_eq!(value("!!5"), Value::Int(1));
+	assert_eq!(value("!\"\""), Value::Int(1));
+	assert_eq!(value("!(1 == 2)"), Value::Int(1));
+	assert_eq!(value("1 != 2"), Value::Int(1));
+}
+
+#[test]
+fn contains_strings_and_chars() {
+	assert_eq!(
+		value("\"bottles of beer\" contains \"beer\""),
+		Value::Int(1)
+	);
+	assert_eq!(
+		value("\"bottles of beer\" contains \"wine\""),
+		Value::Int(0)
+	);
+	assert_eq!(value("\"abc\" contains \"\""), Value::Int(1));
+	assert_eq!(value("\"abc\" contains 'b'"), Value::Int(1));
+	assert_eq!(value("\"abc\" contains 'z'"), Value::Int(0));
+	assert_eq!(value("\"a\\nb\" contains '\\n'"), Value::Int(1));
+	assert_eq!(value("'a'"), Value::Int(97));
+}
+
+#[test]
+fn comparisons() {
+	assert_eq!(value("2 <= 2"), Value::Int(1));
+	assert_eq!(value("2 > 3"), Value::Int(0));
+	assert_eq!(value("\"abc\" < \"abd\""), Value::Int(1));
+	// values of different types are never equal
+	assert_eq!(value("\"1\" == 1"), Value::Int(0));
+}
+
+#[test]
+fn errors() {
+	assert_eq!(error("bottles > 3"), "unknown variable 'bottles'");
+	assert_eq!(error("(1 || 0"), "missing ')'");
+	assert!(error("1 || 0)").starts_with("unexpected"));
+	assert!(error("1 < 2 < 3").starts_with("unexpected"));
+	assert!(error("1 &&").starts_with("unexpected end"));
+	assert!(error("\"abc\" < 1").starts_with("cannot compare"));
+	assert!(error("1 contains 1").starts_with("cannot"));
+	assert_eq!(error("\"abc"), "missing closing \"");
+	assert_eq!(error("'ab'"), "character literals must be one byte");
+	assert_eq!(error("1 = 1"), "unexpected character '='");
+	assert!(error("99999999999999999999").starts_with("number too large"));
+}
+
+#[test]
+fn variables() {
+	let source = "@12?v\n@   ";
+	let mut interpreter = SandWormInterpreter::new(source, b"xyz".to_vec()).unwrap();
+	// the first worm has gone over `1`, `2` and `?`, the second over two spaces and is next
+	interpreter.step(5).unwrap();
+	let value = |source: &str| eval(source, &interpreter).unwrap();
+	assert_eq!(value("steps"), Value::Int(5));
+	assert_eq!(value("worm"), Value::Int(1));
+	assert_eq!(value("worms"), Value::Int(2));
+	assert_eq!(value("len"), Value::Int(0));
+	assert_eq!(value("x"), Value::Int(2));
+	assert_eq!(value("y"), Value::Int(1));
+	assert_eq!(value("dir"), Value::Str(b"right".to_vec()));
+	assert_eq!(value("next"), Value::Int(b' ' as i64));
+	assert_eq!(value("input"), Value::Str(b"xyz".to_vec()));
+	assert_eq!(value("input_index"), Value::Int(1));
+	assert_eq!(value("output"), Value::Str(Vec::new()));
+
+	interpreter.step(1).unwrap();
+	assert_eq!(eval("worm", &interpreter), Ok(Value::Int(0)));
+	assert_eq!(eval("len", &interpreter), Ok(Value::Int(3)));
+	assert_eq!(eval("top", &interpreter), Ok(Value::Int(b'x' as i64)));
+	assert_eq!(eval("next", &interpreter), Ok(Value::Int(b'v' as i64)));
+	assert_eq!(eval("x", &interpreter), Ok(Value::Int(3)));
+}
+
+#[test]
+fn next_is_minus_one_off_the_grid() {
+	let interpreter = SandWormInterpreter::new("@", Vec::new()).unwrap();
+	assert_eq!(eval("next", &interpreter), Ok(Value::Int(-1)));
+	assert_eq!(eval("top", &interpreter), Ok(Value::Int(0)));
+}
+
+#[test]
+fn displays_its_source() {
+	let expr = Expr::parse("  len > 5 && top == '!' ").unwrap();
+	assert_eq!(expr.to_string(), "len > 5 && top == '!'");
+	assert!(expr.is_true(&SandWormInterpreter::new("@", Vec::new()).unwrap()) == Ok(false));
+}
@@ -0,0 +1,137 @@
+use std::fs;
+
+use worm::{History, SandWormInterpreter};
+
+/// run `steps` turns, recording every state, and go back `n`
+fn back_after(source: &str, history: &mut History, steps: usize, n: usize) -> SandWormInterpreter {
+	let mut worm = SandWormInterpreter::new(source, Vec::new()).unwrap();
+	history.record(&worm);
+	for _ in 0..steps {
+		worm.step_once().unwrap();
+		history.record(&worm);
+	}
+	assert_eq!(history.back(&mut worm, n).unwrap(), n);
+	worm
+}
+
+/// the state after running `steps` turns from the start
+fn after(source: &str, steps: usize) -> String {
+	let mut worm = SandWormInterpreter::new(source, Vec::new()).unwrap();
+	worm.step(steps).unwrap();
+	worm.save()
+}
+
+#[test]
+fn going_back_counts_blocked_turns() {
+	let source = "@@    ";
+	let worm = back_after(source, &mut History::default(), 3, 1);
+	assert_eq!(worm.turns(), 2);
+	assert_eq!(worm.save(), after(source, 2));
+}
+
+#[test]
+fn going_back_counts_turns_that_leave_the_grid() {
+	let source = "@ @";
+	let mut worm = SandWormInterpreter::new(source, Vec::new()).unwrap();
+	let mut history = History::default();
+	history.record(&worm);
+	worm.run().unwrap();
+	history.record(&worm);
+	history.back(&mut worm, 1).unwrap();
+	// the second worm left on the second turn and stays gone
+	assert_eq!(worm.save(), after(source, worm.turns()));
+	assert!(!worm.worms()[1].is_alive());
+}
+
+/// run a program with a history, returning the interpreter and the state after every step
+fn record(source: &str, history: &mut History, steps: usize) -> (SandWormInterpreter, Vec<String>) {
+	let mut worm = SandWormInterpreter::new(source, Vec::new()).unwrap();
+	let mut states = vec![worm.save()];
+	history.record(&worm);
+	for _ in 0..steps {
+		worm.step_once().unwrap();
+		history.record(&worm);
+		states.push(worm.save());
+	}
+	(worm, states)
+}
+
+fn double_loop() -> String {
+	fs::read_to_string("programs/double_loop.worm").unwrap()
+}
+
+#[test]
+fn going_back_across_checkpoints() {
+	let mut history = History::new(10, usize::MAX);
+	let (mut worm, states) = record(&double_loop(), &mut history, 95);
+	// lands between checkpoints, then exactly on one, then further back across several
+	for (n, expected) in [(3, 92), (2, 90), (25, 65), (1, 64)] {
+		assert_eq!(history.back(&mut worm, n).unwrap(), n);
+		assert_eq!(worm.turns(), expected);
+		assert_eq!(worm.save(), states[expected]);
+	}
+	// stepping forward again keeps recording from there
+	worm.step_once().unwrap();
+	history.record(&worm);
+	assert_eq!(worm.save(), states[65]);
+	history.back(&mut worm, 1).unwrap();
+	assert_eq!(worm.save(), states[64]);
+}
+
+#[test]
+fn going_back_stops_at_the_oldest_checkpoint() {
+	// only room for a few checkpoints of 10 turns
+	let mut history = History::new(10, 15_000);
+	let (mut worm, states) = record(&double_loop(), &mut history, 95);
+	assert!(history.size() <= 15_000);
+	let undone = history.back(&mut worm, 60).unwrap();
+	assert!(undone < 60);
+	assert_eq!(undone % 10, 5);
+	assert_eq!(worm.save(), states[95 - undone]);
+	assert_eq!(history.back(&mut worm, 1).unwrap(), 0);
+	assert_eq!(worm.save(), states[95 - undone]);
+}
+
+#[test]
+fn output_is_not_kept_in_every_checkpoint() {
+	let run_recording = |source: &str| {
+		let mut worm = SandWormInterpreter::new(source, Vec::new()).unwrap();
+		worm.set_topology(worm::Topology::Torus);
+		let mut history = History::new(10, usize::MAX);
+		history.record(&worm);
+		for _ in 0..5000 {
+			worm.step_once().unwrap();
+			history.record(&worm);
+		}
+		(history, worm)
+	};
+	// the same worm, printing every value or dropping it
+	let (mut history, mut worm) = run_recording("@1\"");
+	let (silent, _) = run_recording("@1$");
+	assert_eq!(worm.output().len(), 2500);
+	assert_eq!(history.size(), silent.size());
+	history.back(&mut worm, 1000).unwrap();
+	assert_eq!(worm.output(), [b'1'; 2000]);
+}
+
+#[test]
+fn going_back_from_the_end() {
+	let mut history = History::new(4, usize::MAX);
+	let (mut worm, states) = record("@12+\"  ", &mut history, 20);
+	let end = worm.turns();
+	assert!(end < 20);
+	assert_eq!(history.back(&mut worm, 1).unwrap(), 1);
+	assert_eq!(worm.save(), states[end - 1]);
+	assert_eq!(worm.state(), worm::State::Running);
+	worm.run().unwrap();
+	assert_eq!(worm.save(), states[end]);
+	assert_eq!(worm.output(), b"3");
+}
+
+#[test]
+fn empty_history_goes_nowhere() {
+	let mut worm = SandWormInterpreter::new("@1 ", Vec::new()).unwrap();
+	worm.step_once().unwrap();
+	assert_eq!(History::default().back(&mut worm, 1).unwrap(), 0);
+	assert_eq!(worm.turns(), 1);
+}
@@ -0,0 +1,61 @@
+use std::io::Cursor;
+
+use worm::{Input, SandWormInterpreter};
+
+fn read_all(input: &mut Input) -> Vec<u8> {
+	(0..).map_while(|i| input.get(i).unwrap()).collect()
+}
+
+#[test]
+fn pushed_input_comes_after_the_source() {
+	let mut worm = SandWormInterpreter::new("@????? ", Vec::new()).unwrap();
+	worm.set_input_source(Cursor::new(b"abc".to_vec()));
+	worm.push_input(b"XY");
+	worm.step(5).unwrap();
+	assert_eq!(worm.worm().values(), b"abcXY");
+}
+
+#[test]
+fn pushed_input_waits_for_a_source_that_has_started() {
+	let mut input = Input::from_reader(Cursor::new(b"abc".to_vec()));
+	assert_eq!(input.get(0).unwrap(), Some(b'a'));
+	input.extend(b"XY");
+	assert_eq!(input.buffered(), b"a");
+	assert_eq!(input.queued(), b"XY");
+	assert_eq!(read_all(&mut input), b"abcXY");
+	input.extend(b"Z");
+	assert_eq!(input.buffered(), b"abcXYZ");
+}
+
+#[test]
+fn earlier_input_comes_before_a_new_source() {
+	let mut worm = SandWormInterpreter::new("@???? ", b"12".to_vec()).unwrap();
+	worm.set_input_source(Cursor::new(b"34".to_vec()));
+	worm.step(4).unwrap();
+	assert_eq!(worm.worm().values(), b"1234");
+}
+
+#[test]
+fn pushed_input_waits_for_a_new_source_too() {
+	let mut worm = SandWormInterpreter::new("@?????? ", Vec::new()).unwrap();
+	worm.set_input_source(Cursor::new(b"ab".to_vec()));
+	worm.step(1).unwrap();
+	worm.push_input(b"XY");
+	worm.set_input_source(Cursor::new(b"cd".to_vec()));
+	worm.step(5).unwrap();
+	// the rest of the old source is gone, but not what was pushed
+	assert_eq!(worm.worm().values(), b"acdXY\0");
+}
+
+#[test]
+fn interpreters_can_move_to_another_thread() {
+	let mut worm = SandWormInterpreter::new("@??? ", Vec::new()).unwrap();
+	worm.set_input_source(Cursor::new(b"abc".to_vec()));
+	let worm = std::thread::spawn(move || {
+		worm.run().unwrap();
+		worm
+	})
+	.join()
+	.unwrap();
+	assert_eq!(&*worm.input(), b"abc");
+}
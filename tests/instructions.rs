@@ -0,0 +1,391 @@
+//! what each instruction does to the grid, the worm and its queues, one tiny program at a time
+
+use worm::{Direction, SandWormInterpreter, State};
+
+fn run(source: &str, steps: usize) -> SandWormInterpreter {
+	run_with_input(source, b"", steps)
+}
+
+fn run_with_input(source: &str, input: &[u8], steps: usize) -> SandWormInterpreter {
+	let mut worm = SandWormInterpreter::new(source, input.to_vec()).unwrap();
+	worm.step(steps).unwrap();
+	worm
+}
+
+fn worm_out(worm: &SandWormInterpreter) -> Vec<u8> {
+	worm.worm().worm_out().iter().copied().collect()
+}
+
+#[test]
+fn digit_grows_the_worm_and_is_left_behind_the_tail() {
+	let mut worm = run("@3  ", 1);
+	assert_eq!(worm.program()[0], b"\x03@  ");
+	assert_eq!(worm.worm().body(), [(0, 0)]);
+	assert_eq!(worm.worm().values(), [3]);
+	assert_eq!(worm.worm().head(), (1, 0));
+	assert_eq!(worm_out(&worm), b"3");
+
+	worm.step_once().unwrap();
+	assert_eq!(worm.program()[0], b"3\x03@ ");
+	assert_eq!(worm.worm().body(), [(1, 0)]);
+	assert!(worm_out(&worm).is_empty());
+}
+
+#[test]
+fn space_moves_without_growing() {
+	let worm = run("@   ", 2);
+	assert_eq!(worm.program()[0], b"  @ ");
+	assert!(worm.worm().body().is_empty());
+	assert!(worm_out(&worm).is_empty());
+}
+
+#[test]
+fn empty_cells_are_not_left_behind() {
+	let worm = run("@ \n  ", 1);
+	assert_eq!(worm.program()[0], b" @");
+	assert!(worm_out(&worm).is_empty());
+}
+
+#[test]
+fn plus_queues_the_operator_between_the_two_pops() {
+	let mut worm = run("@12+  ", 3);
+	// popping 2 leaves the queued `1` behind the tail, then `+` is queued before popping 1
+	assert_eq!(worm.program()[0], b"12\x03@  ");
+	assert_eq!(worm.worm().values(), [3]);
+	assert_eq!(worm_out(&worm), b"+");
+
+	worm.step_once().unwrap();
+	assert_eq!(worm.program()[0], b"12+\x03@ ");
+}
+
+#[test]
+fn minus_subtracts_the_second_pop_from_the_first() {
+	let worm = run("@52- ", 3);
+	assert_eq!(worm.worm().values(), [2u8.wrapping_sub(5)]);
+	let worm = run("@25- ", 3);
+	assert_eq!(worm.worm().values(), [3]);
+}
+
+#[test]
+fn arithmetic_on_an_empty_worm_uses_zeros() {
+	let worm = run("@+ ", 1);
+	assert_eq!(worm.worm().values(), [0]);
+	assert_eq!(worm.program()[0], b"\x00@ ");
+	assert_eq!(worm_out(&worm), b"+");
+
+	let worm = run("@3- ", 2);
+	assert_eq!(worm.worm().values(), [3]);
+}
+
+#[test]
+fn arithmetic_wraps() {
+	let worm = run("@99+=+=+=+=+ ", 11);
+	assert_eq!(worm.worm().values(), [(18u32 * 16 % 256) as u8]);
+}
+
+#[test]
+fn star_multiplies() {
+	let worm = run("@34* ", 3);
+	assert_eq!(worm.worm().values(), [12]);
+	assert_eq!(worm.program()[0], b"34\x0c@ ");
+	let worm = run("@99*=* ", 5);
+	assert_eq!(worm.worm().values(), [(81u32 * 81 % 256) as u8]);
+}
+
+#[test]
+fn semicolon_divides_the_first_pop_by_the_second() {
+	let worm = run("@27; ", 3);
+	assert_eq!(worm.worm().values(), [3]);
+	let worm = run("@72; ", 3);
+	assert_eq!(worm.worm().values(), [0]);
+}
+
+#[test]
+fn percent_is_the_remainder() {
+	let worm = run("@38% ", 3);
+	assert_eq!(worm.worm().values(), [2]);
+	let worm = run("@83% ", 3);
+	assert_eq!(worm.worm().values(), [3]);
+}
+
+#[test]
+fn dividing_by_zero_gives_zero() {
+	let worm = run("@07; ", 3);
+	assert_eq!(worm.worm().values(), [0]);
+	let worm = run("@07% ", 3);
+	assert_eq!(worm.worm().values(), [0]);
+	let worm = run("@5; ", 2);
+	assert_eq!(worm.worm().values(), [0]);
+}
+
+#[test]
+fn arrows_turn_the_worm() {
+	let worm = run("@ v\n   \n  > ", 1);
+	assert_eq!(worm.worm().direction(), Direction::Right);
+	let mut worm = run("@ v\n   \n < ", 2);
+	assert_eq!(worm.worm().direction(), Direction::Down);
+	assert_eq!(worm.worm().head(), (2, 0));
+	worm.step(2).unwrap();
+	assert_eq!(worm.worm().head(), (2, 2));
+	// arrows are not values, but they are still left behind, one cell back
+	assert!(worm.worm().values().is_empty());
+	assert_eq!(worm.program()[0], b" v ");
+
+	let worm = run("@ v\n ^<", 4);
+	assert_eq!(worm.worm().direction(), Direction::Up);
+	assert_eq!(worm.worm().head(), (1, 1));
+}
+
+#[test]
+fn bang_writes_a_byte() {
+	let worm = run("@AB!! ", 5);
+	assert_eq!(worm.output(), b"BA");
+	assert!(worm.worm().values().is_empty());
+}
+
+#[test]
+fn quote_writes_a_number() {
+	let worm = run("@99+\" ", 4);
+	assert_eq!(worm.output(), b"18");
+	let worm = run("@0-\"  ", 3);
+	assert_eq!(worm.output(), b"0");
+}
+
+#[test]
+fn output_from_an_empty_worm_is_zero() {
+	let worm = run("@!\" ", 2);
+	assert_eq!(worm.output(), b"\x000");
+}
+
+#[test]
+fn question_mark_reads_input_then_zero() {
+	let worm = run_with_input("@??? ", b"hi", 3);
+	assert_eq!(worm.worm().values(), b"hi\0");
+	assert_eq!(worm.input_index(), 3);
+}
+
+#[test]
+fn ampersand_reads_a_number() {
+	let worm = run_with_input("@&&?& ", b" 42\n\t 7x", 4);
+	assert_eq!(worm.worm().values(), [42, 7, b'x', 0]);
+	assert_eq!(worm.input_index(), 8);
+}
+
+#[test]
+fn ampersand_without_digits_pushes_zero() {
+	let worm = run_with_input("@&? ", b"abc", 2);
+	assert_eq!(worm.worm().values(), [0, b'a']);
+	let worm = run_with_input("@& ", b"  ", 1);
+	assert_eq!(worm.worm().values(), [0]);
+}
+
+#[test]
+fn ampersand_wraps_past_255() {
+	let worm = run_with_input("@& ", b"300", 1);
+	assert_eq!(worm.worm().values(), [44]);
+}
+
+#[test]
+fn equals_duplicates_the_top() {
+	let worm = run("@12= ", 3);
+	assert_eq!(worm.worm().values(), [1, 2, 2]);
+	assert_eq!(worm_out(&worm), b"=21");
+}
+
+#[test]
+fn equals_on_an_empty_worm_pushes_zero() {
+	let worm = run("@= ", 1);
+	assert_eq!(worm.worm().values(), [0]);
+}
+
+#[test]
+fn dollar_drops_the_top() {
+	let worm = run("@12$ ", 3);
+	assert_eq!(worm.worm().values(), [1]);
+	// the queued `1` and `2` are left behind as the body shortens and moves on
+	assert_eq!(worm.program()[0], b"12\x01@ ");
+	assert_eq!(worm_out(&worm), b"$");
+	assert!(worm.output().is_empty());
+
+	let worm = run("@$ ", 1);
+	assert!(worm.worm().values().is_empty());
+	assert_eq!(worm.program()[0], b"$@ ");
+}
+
+#[test]
+fn hash_swaps_the_top_two() {
+	let worm = run("@123# ", 4);
+	assert_eq!(worm.worm().values(), [1, 3, 2]);
+	assert_eq!(worm.worm().body(), [(1, 0), (2, 0), (3, 0)]);
+	assert_eq!(worm.program()[0], b"1\x01\x03\x02@ ");
+	assert_eq!(worm_out(&worm), b"#32");
+}
+
+#[test]
+fn backtick_rotates_the_third_to_the_top() {
+	let worm = run("@1234` ", 5);
+	assert_eq!(worm.worm().values(), [1, 3, 4, 2]);
+	assert_eq!(worm.program()[0], b"1\x01\x03\x04\x02@ ");
+}
+
+#[test]
+fn swap_and_rotate_need_enough_values() {
+	let worm = run("@1# ", 2);
+	assert_eq!(worm.worm().values(), [1]);
+	let worm = run("@12` ", 3);
+	assert_eq!(worm.worm().values(), [1, 2]);
+}
+
+#[test]
+fn open_bracket_reads_a_cell() {
+	let worm = run("@60[  A", 4);
+	assert_eq!(worm.worm().values(), [b'A']);
+	assert_eq!(worm.program()[0], b"60[\x41@ A");
+}
+
+#[test]
+fn reading_outside_the_grid_gives_zero() {
+	let worm = run("@90[ ", 4);
+	assert_eq!(worm.worm().values(), [0]);
+}
+
+#[test]
+fn close_bracket_writes_a_cell() {
+	let worm = run("@780]   x", 5);
+	assert_eq!(worm.program()[0], b"780] @  \x07");
+	assert!(worm.worm().values().is_empty());
+}
+
+#[test]
+fn writing_outside_the_grid_is_lost() {
+	let worm = run("@790] \n     ", 5);
+	assert_eq!(worm.program()[0], b"790] @");
+	assert_eq!(worm.program()[1], b"     \0");
+}
+
+#[test]
+fn writing_onto_a_body_changes_the_stack() {
+	// the value, x and y are popped, leaving 5 at x = 3 to be overwritten
+	let worm = run("@5930] ", 5);
+	assert_eq!(worm.worm().values(), [9]);
+	assert_eq!(worm.get((4, 0)), Some(9));
+}
+
+#[test]
+fn writing_onto_a_head_is_ignored() {
+	// the second worm writes 9 where the first worm's head is by then
+	let worm = run("@       \n@940]   ", 8);
+	assert_eq!(worm.worms()[0].head(), (4, 0));
+	assert_eq!(worm.get((4, 0)), Some(b'@'));
+	assert!(!worm.program()[0].contains(&9));
+}
+
+#[test]
+fn tilde_is_logical_not() {
+	assert_eq!(run("@0~ ", 2).worm().values(), [1]);
+	assert_eq!(run("@7~ ", 2).worm().values(), [0]);
+	assert_eq!(run("@~ ", 1).worm().values(), [1]);
+}
+
+#[test]
+fn mirrors_only_reflect_on_nonzero() {
+	let worm = run("@0\\ \n    ", 3);
+	assert_eq!(worm.worm().direction(), Direction::Right);
+	assert_eq!(worm.worm().head(), (3, 0));
+
+	let worm = run("@1\\ \n    ", 3);
+	assert_eq!(worm.worm().direction(), Direction::Down);
+	assert_eq!(worm.worm().head(), (2, 1));
+
+	let worm = run("\n@1/ \n    ", 3);
+	assert_eq!(worm.worm().direction(), Direction::Up);
+	assert_eq!(worm.worm().head(), (2, 0));
+	// the value is used up either way
+	assert!(worm.worm().values().is_empty());
+}
+
+#[test]
+fn mirrors_on_an_empty_worm_do_nothing() {
+	let worm = run("@/ \n   ", 1);
+	assert_eq!(worm.worm().direction(), Direction::Right);
+	// with no body, the mirror is left right where the head was
+	assert_eq!(worm.program()[0], b"/@ ");
+	assert!(worm_out(&worm).is_empty());
+}
+
+#[test]
+fn mirror_directions() {
+	let cases = [
+		("@1\\\n   \n   ", 2, Direction::Down),
+		("   \n@1/\n   ", 2, Direction::Up),
+		("@ v\n  1\n  \\\n   ", 4, Direction::Right),
+		("@ v\n  1\n  /\n   ", 4, Direction::Left),
+		("@ v\n   \n\\1<", 6, Direction::Up),
+		("@ v\n   \n/1<", 6, Direction::Down),
+	];
+	for (source, steps, direction) in cases {
+		let worm = run(source, steps);
+		assert_eq!(worm.worm().direction(), direction, "{source:?}");
+	}
+}
+
+#[test]
+fn underscore_pushes_a_space() {
+	let worm = run("@_ ", 1);
+	assert_eq!(worm.worm().values(), b" ");
+	assert_eq!(worm_out(&worm), b"_");
+}
+
+#[test]
+fn other_characters_push_themselves() {
+	let worm = run("@a ", 1);
+	assert_eq!(worm.worm().values(), b"a");
+	let worm = run("@\t ", 1);
+	assert_eq!(worm.worm().values(), b"\t");
+}
+
+#[test]
+fn dot_halts_with_an_exit_code() {
+	let mut worm = run("@42. ", 3);
+	assert_eq!(worm.state(), State::Halted(2));
+	assert_eq!(worm.worm().values(), [4]);
+	assert_eq!(worm.worm().head(), (3, 0));
+	worm.step_once().unwrap();
+	assert_eq!(worm.steps(), 3);
+
+	let worm = run("@. ", 1);
+	assert_eq!(worm.state(), State::Halted(0));
+}
+
+#[test]
+fn dot_stops_every_worm() {
+	let worm = run("@.  \n@   ", 5);
+	assert_eq!(worm.state(), State::Halted(0));
+	assert_eq!(worm.steps(), 1);
+	assert_eq!(worm.worms()[1].head(), (0, 1));
+}
+
+#[test]
+fn leaving_the_grid_ends_the_program() {
+	let worm = run("@1", 2);
+	assert_eq!(worm.state(), State::EndOfProgram);
+	assert_eq!(worm.steps(), 1);
+	// the body stays behind
+	assert_eq!(worm.program()[0], b"\x01@");
+}
+
+#[test]
+fn moving_into_its_own_body_runs_over_it() {
+	let mut worm = run("@123v\n   ^<", 6);
+	assert_eq!(worm.worm().head(), (3, 1));
+	assert_eq!(worm.worm().body(), [(3, 0), (4, 0), (4, 1)]);
+	worm.step_once().unwrap();
+	// the value under the head is the instruction, and that segment is now an `@`
+	assert_eq!(worm.last_step().instruction, Some(1));
+	assert_eq!(worm.steps(), 7);
+	assert_eq!(worm.worm().head(), (3, 0));
+	assert_eq!(worm.worm().body(), [(3, 0), (4, 0), (4, 1), (3, 1)]);
+	assert_eq!(worm.worm().values(), [b'@', 2, 3, 1]);
+	worm.run().unwrap();
+	assert_eq!(worm.state(), State::EndOfProgram);
+}
@@ -0,0 +1,135 @@
+use worm::{parse, Diagnostic, Severity};
+
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+	parse(source).diagnostics
+}
+
+/// the only diagnostic for `source`
+fn only(source: &str) -> Diagnostic {
+	let mut diagnostics = diagnostics(source);
+	assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+	diagnostics.remove(0)
+}
+
+#[test]
+fn clean_programs_have_no_diagnostics() {
+	let parsed = parse("@12+\nv  <\n>  ^");
+	assert!(parsed.diagnostics.is_empty());
+	assert!(!parsed.has_errors());
+	assert_eq!(parsed.start_positions, [(0, 0)]);
+}
+
+#[test]
+fn missing_head_is_an_error() {
+	let diagnostic = only("12+");
+	assert_eq!(diagnostic.severity, Severity::Error);
+	assert_eq!(diagnostic.message, "no worm head '@' to start from");
+	assert_eq!((diagnostic.line, diagnostic.column), (1, 1));
+	assert!(parse("12+").has_errors());
+}
+
+#[test]
+fn empty_program_is_an_error() {
+	for source in ["", "\n\n"] {
+		let diagnostic = only(source);
+		assert_eq!(diagnostic.severity, Severity::Error);
+		assert_eq!(diagnostic.message, "program is empty");
+	}
+}
+
+#[test]
+fn tab() {
+	let diagnostic = only("@1\t2");
+	assert_eq!(diagnostic.severity, Severity::Warning);
+	assert!(diagnostic.message.starts_with("tab"));
+	assert_eq!(
+		(diagnostic.line, diagnostic.column, diagnostic.len),
+		(1, 3, 1)
+	);
+}
+
+#[test]
+fn crlf() {
+	let parsed = parse("@1\r\n23\r\n");
+	assert_eq!(parsed.program, [b"@1", b"23"]);
+	let columns: Vec<_> = parsed
+		.diagnostics
+		.iter()
+		.map(|d| (d.line, d.column, d.message.contains("CRLF")))
+		.collect();
+	assert_eq!(columns, [(1, 3, true), (2, 3, true)]);
+}
+
+#[test]
+fn trailing_whitespace() {
+	let diagnostic = only("@1\n2   ");
+	assert_eq!(diagnostic.message, "trailing whitespace");
+	assert_eq!(
+		(diagnostic.line, diagnostic.column, diagnostic.len),
+		(2, 2, 3)
+	);
+}
+
+#[test]
+fn non_ascii_spans_every_byte_of_the_character() {
+	// é is 2 bytes and 😀 is 4, so the emoji starts at byte column 5
+	let diagnostics = diagnostics("@é1😀");
+	let spans: Vec<_> = diagnostics.iter().map(|d| (d.column, d.len)).collect();
+	assert_eq!(spans, [(2, 2), (5, 4)]);
+	assert!(diagnostics
+		.iter()
+		.all(|d| d.message.starts_with("non-ASCII")));
+	assert_eq!(parse("@é").program, ["@é".as_bytes()]);
+}
+
+#[test]
+fn prose() {
+	let diagnostic = only("@\nThis program prints the input.");
+	assert!(diagnostic.message.starts_with("this looks like prose"));
+	assert_eq!((diagnostic.line, diagnostic.column), (2, 1));
+	assert_eq!(diagnostic.len, "This program prints the input.".len());
+	// worm code with the odd word in it is fine
+	assert!(diagnostics("@\n_bottles_of_beer  v  12+").is_empty());
+	assert!(diagnostics("@\nv  <  hi").is_empty());
+}
+
+#[test]
+fn diagnostics_are_in_source_order() {
+	let lines: Vec<_> = diagnostics("1\t \n\t@")
+		.iter()
+		.map(|d| (d.line, d.column))
+		.collect();
+	assert_eq!(lines, [(1, 2), (1, 3), (2, 1)]);
+}
+
+#[test]
+fn render_underlines_the_span() {
+	let source = "@1\n2   ";
+	let rendered = only(source).render("test.worm", source);
+	assert_eq!(
+		rendered,
+		"warning: trailing whitespace
+ --> test.worm:2:2
+  |
+2 | 2   
+  |  ^^^
+"
+	);
+}
+
+#[test]
+fn render_counts_characters_not_bytes() {
+	let source = "@é😀";
+	let diagnostics = diagnostics(source);
+	let rendered = diagnostics[1].render("x.worm", source);
+	assert!(rendered.contains(" --> x.worm:1:4\n"), "{rendered}");
+	// the caret sits under the emoji, one column per character
+	assert!(rendered.ends_with("1 | @é😀\n  |   ^\n"), "{rendered}");
+}
+
+#[test]
+fn render_shows_tabs_as_spaces_and_drops_the_cr() {
+	let source = "@\t1\r\n";
+	let rendered = diagnostics(source)[0].render("t.worm", source);
+	assert!(rendered.ends_with("1 | @ 1\n  |  ^\n"), "{rendered}");
+}
@@ -0,0 +1,51 @@
+//! golden output tests for the programs in `programs/`, the same as `worm test`
+
+use std::path::Path;
+
+use worm::{diff, GoldenTest};
+
+#[test]
+fn programs_match_expected_output() {
+	let tests = GoldenTest::find(Path::new("programs")).unwrap();
+	assert!(tests.len() >= 3, "expected outputs are missing");
+	let failures: Vec<_> = tests
+		.iter()
+		.filter_map(|test| {
+			let result = test.run();
+			let failure = result.failure?;
+			Some(format!("{} ({} steps): {failure}", test.name, result.steps))
+		})
+		.collect();
+	assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn diff_shows_changed_lines_with_context() {
+	let expected = b"one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+	let actual = b"one\ntwo\nthree\nfour\n5\nsix\nseven\n";
+	assert_eq!(
+		diff(expected, actual),
+		"  three\n  four\n- five\n+ 5\n  six\n  seven\n"
+	);
+}
+
+#[test]
+fn diff_escapes_unprintable_bytes() {
+	assert_eq!(diff(b"a", b"a\0"), "- a\n+ a\\x00\n");
+}
+
+#[test]
+fn diff_of_large_outputs_shows_the_first_difference() {
+	let expected: String = (0..2000).map(|i| format!("{i}\n")).collect();
+	let actual: String = (0..2000).map(|i| format!("{}\n", i * 2)).collect();
+	assert_eq!(
+		diff(expected.as_bytes(), actual.as_bytes()),
+		"  0\n- 1\n+ 2\n... too many lines differ to show the rest\n"
+	);
+	// unchanged lines around a small change are not compared
+	let changed = expected.replace("\n1500\n", "\nfifteen hundred\n");
+	assert_eq!(
+		diff(expected.as_bytes(), changed.as_bytes()),
+		"  1498\n  1499\n- 1500\n+ fifteen hundred\n  1501\n  1502\n"
+	);
+}
@@ -0,0 +1,90 @@
+use std::fs;
+
+use worm::{draw_grid, Cell, Occupancy, SandWormInterpreter, Topology, Viewport};
+
+/// the map kept up to date step by step matches one filled from scratch
+fn assert_in_sync(occupancy: &mut Occupancy, interpreter: &SandWormInterpreter, context: &str) {
+	occupancy.update(interpreter);
+	let mut fresh = Occupancy::default();
+	fresh.update(interpreter);
+	for y in 0..interpreter.height() {
+		for x in 0..interpreter.width() {
+			assert_eq!(
+				occupancy.get((x, y)),
+				fresh.get((x, y)),
+				"{x} {y}, {context}"
+			);
+		}
+	}
+}
+
+fn follow(source: &str, topology: Topology, steps: usize, every: usize) {
+	let mut interpreter = SandWormInterpreter::new(source, b"some input".to_vec()).unwrap();
+	interpreter.set_topology(topology);
+	let mut occupancy = Occupancy::default();
+	for step in 0..steps {
+		if step % every == 0 {
+			assert_in_sync(&mut occupancy, &interpreter, &format!("step {step}"));
+		}
+		interpreter.step_once().unwrap();
+	}
+	assert_in_sync(&mut occupancy, &interpreter, "the end");
+}
+
+#[test]
+fn stays_in_sync_with_the_worms() {
+	for name in ["99_bottles_of_beer", "cat", "double_loop", "hello_world"] {
+		let source = fs::read_to_string(format!("programs/{name}.worm")).unwrap();
+		follow(&source, Topology::Bounded, 3000, 1);
+		follow(&source, Topology::Bounded, 20_000, 997);
+	}
+	follow("@123\n @45  \n  @6   ", Topology::Torus, 200, 1);
+	follow("@12", Topology::Torus, 50, 1);
+	follow("@123v\n   ^<", Topology::Bounded, 10, 1);
+	follow("@12 \n @3 ", Topology::Unbounded, 200, 3);
+}
+
+#[test]
+fn going_back_needs_a_clear() {
+	let source = fs::read_to_string("programs/double_loop.worm").unwrap();
+	let mut interpreter = SandWormInterpreter::new(&source, Vec::new()).unwrap();
+	let start = interpreter.clone();
+	interpreter.step(500).unwrap();
+	let mut occupancy = Occupancy::default();
+	occupancy.update(&interpreter);
+	occupancy.clear();
+	assert_in_sync(&mut occupancy, &start, "after going back");
+}
+
+#[test]
+fn draws_body_values_and_heads() {
+	let mut interpreter = SandWormInterpreter::new("@12   \n@", Vec::new()).unwrap();
+	interpreter.step(4).unwrap();
+	let mut occupancy = Occupancy::default();
+	occupancy.update(&interpreter);
+	assert_eq!(occupancy.get((2, 0)), Some(Cell::Head(0)));
+	assert_eq!(occupancy.get((1, 0)), Some(Cell::Body(0)));
+	let view = Viewport {
+		x: 0,
+		y: 0,
+		width: 4,
+		height: 1,
+	};
+	let mut out = Vec::new();
+	draw_grid(&interpreter, &occupancy, view, &mut out).unwrap();
+	assert_eq!(strip_colors(&out), "12@ ");
+}
+
+fn strip_colors(bytes: &[u8]) -> String {
+	let text = String::from_utf8(bytes.to_vec()).unwrap();
+	let mut plain = String::new();
+	let mut chars = text.chars();
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			chars.find(|&c| c == 'm');
+		} else {
+			plain.push(c);
+		}
+	}
+	plain
+}
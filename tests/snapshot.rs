@@ -0,0 +1,139 @@
+use std::fs;
+
+use worm::{SandWormInterpreter, State, Topology, WormError};
+
+/// saving and loading at `steps` gives an interpreter that carries on the same way
+fn assert_round_trip(mut worm: SandWormInterpreter, steps: usize) {
+	worm.step(steps).unwrap();
+	let saved = worm.save();
+	let mut loaded = SandWormInterpreter::load(&saved).unwrap();
+	assert_eq!(loaded.save(), saved);
+	assert_eq!(loaded.program(), worm.program());
+
+	// errors leave the state as it was, so they can be compared too
+	let result = worm.step(500).map_err(|err| err.to_string());
+	assert_eq!(loaded.step(500).map_err(|err| err.to_string()), result);
+	assert_eq!(loaded.save(), worm.save());
+}
+
+#[test]
+fn example_programs_resume_where_they_were_saved() {
+	for name in ["99_bottles_of_beer", "cat", "double_loop", "hello_world"] {
+		let source = fs::read_to_string(format!("programs/{name}.worm")).unwrap();
+		for steps in [0, 1, 37, 1000] {
+			let worm = SandWormInterpreter::new(&source, b"some\ninput \"quoted\"\xff".to_vec());
+			assert_round_trip(worm.unwrap(), steps);
+		}
+	}
+}
+
+#[test]
+fn multiple_worms_and_topology_are_saved() {
+	let mut worm = SandWormInterpreter::new("@12 \n @3 ", Vec::new()).unwrap();
+	worm.set_topology(Topology::Torus);
+	assert_round_trip(worm, 5);
+
+	let mut worm = SandWormInterpreter::new("@ \n@  ", Vec::new()).unwrap();
+	worm.step(10).unwrap();
+	let loaded = SandWormInterpreter::load(&worm.save()).unwrap();
+	assert_eq!(loaded.state(), worm.state());
+	assert_eq!(loaded.steps(), 4);
+}
+
+#[test]
+fn worms_on_top_of_themselves_are_saved() {
+	for steps in [2, 3, 4, 20] {
+		assert_round_trip(SandWormInterpreter::new("@12", Vec::new()).unwrap(), steps);
+		let mut worm = SandWormInterpreter::new("@12", Vec::new()).unwrap();
+		worm.set_topology(Topology::Torus);
+		assert_round_trip(worm, steps);
+	}
+}
+
+#[test]
+fn exit_code_is_saved() {
+	let mut worm = SandWormInterpreter::new("@7. ", Vec::new()).unwrap();
+	worm.step(2).unwrap();
+	let saved = worm.save();
+	assert!(saved.contains("\nstate halted 7\n"));
+	let loaded = SandWormInterpreter::load(&saved).unwrap();
+	assert_eq!(loaded.state(), State::Halted(7));
+}
+
+#[test]
+fn snapshot_format() {
+	let mut worm = SandWormInterpreter::new("@1\\\n  _", b"x".to_vec()).unwrap();
+	worm.step(2).unwrap();
+	assert_eq!(
+		worm.save(),
+		r#"worm snapshot 1
+size 3 2
+origin 0 0
+topology bounded
+state running
+steps 2
+current 0
+blocked 0
+input_index 0
+input "x"
+output ""
+worms 1
+worm 2 0 down alive
+body 
+in ""
+out ""
+grid
+"1\\@"
+"  _"
+"#
+	);
+}
+
+#[test]
+fn invalid_snapshots_report_the_line() {
+	let saved = SandWormInterpreter::new("@1 ", Vec::new()).unwrap().save();
+	let broken = saved.replace("topology bounded", "topology sideways");
+	match SandWormInterpreter::load(&broken) {
+		Err(WormError::Snapshot { line: 4, .. }) => (),
+		other => panic!("expected an error on line 4, got {other:?}"),
+	}
+	let truncated: String = saved.lines().take(10).collect::<Vec<_>>().join("\n");
+	assert!(SandWormInterpreter::load(&truncated).is_err());
+	assert!(SandWormInterpreter::load("worm snapshot 2\n").is_err());
+}
+
+#[test]
+fn inconsistent_snapshots_are_rejected() {
+	let saved = SandWormInterpreter::new("@1 \n@  ", Vec::new())
+		.unwrap()
+		.save();
+	let load_error = |text: String| match SandWormInterpreter::load(&text) {
+		Err(WormError::Snapshot { message, .. }) => message,
+		other => panic!("expected an error, got {other:?}"),
+	};
+
+	let far_origin = saved.replace("origin 0 0", "origin 18446744073709551615 0");
+	assert_eq!(load_error(far_origin), "origin is outside the grid");
+	assert!(SandWormInterpreter::load(&saved.replace("origin 0 0", "origin 0 2")).is_err());
+	assert!(SandWormInterpreter::load(&saved.replace("origin 0 0", "origin 2 1")).is_ok());
+
+	// both heads on the same cell
+	let same_head = saved.replace("worm 0 1 right alive", "worm 0 0 right alive");
+	assert_eq!(load_error(same_head), "worm overlaps another worm");
+	// a body on the other worm's head
+	let on_head = saved.replace(
+		"worm 0 1 right alive\nbody ",
+		"worm 1 1 right alive\nbody 0,0",
+	);
+	assert_eq!(load_error(on_head), "worm overlaps another worm");
+	// a worm that has left can overlap anything
+	let left = saved.replace("worm 0 1 right alive", "worm 0 0 right dead");
+	assert!(SandWormInterpreter::load(&left).is_ok());
+
+	let dead_current = saved.replace("worm 0 0 right alive", "worm 0 0 right dead");
+	assert!(load_error(dead_current).starts_with("the current worm has left"));
+	let ended = saved
+		.replace("worm 0 0 right alive", "worm 0 0 right dead")
+		.replace("state running", "state ended");
+	assert!(SandWormInterpreter::load(&ended).is_ok());
+}
@@ -0,0 +1,139 @@
+use worm::{Breakpoint, Expr, SandWormInterpreter, State, Topology, Value};
+
+fn interpreter(source: &str, topology: Topology) -> SandWormInterpreter {
+	let mut interpreter = SandWormInterpreter::new(source, Vec::new()).unwrap();
+	interpreter.set_topology(topology);
+	interpreter
+}
+
+#[test]
+fn bounded_ends_at_edge() {
+	let mut worm = interpreter("4 @", Topology::Bounded);
+	worm.step_once().unwrap();
+	assert_eq!(worm.state(), State::EndOfProgram);
+	assert_eq!(worm.worm().head(), (2, 0));
+	assert_eq!(worm.steps(), 0);
+}
+
+#[test]
+fn wrap_right_edge() {
+	let mut worm = interpreter("4 @", Topology::Torus);
+	worm.step_once().unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (0, 0));
+	assert_eq!(worm.worm().body(), [(2, 0)]);
+	assert_eq!(worm.get((2, 0)), Some(4));
+}
+
+#[test]
+fn wrap_left_edge() {
+	let mut worm = interpreter("@<  ", Topology::Torus);
+	worm.step(2).unwrap();
+	assert_eq!(worm.worm().head(), (0, 0));
+	worm.step_once().unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (3, 0));
+
+	let mut bounded = interpreter("@<  ", Topology::Bounded);
+	bounded.step(3).unwrap();
+	assert_eq!(bounded.state(), State::EndOfProgram);
+	assert_eq!(bounded.worm().head(), (0, 0));
+}
+
+#[test]
+fn wrap_top_edge() {
+	let source = "@^\n  \n 4";
+	let mut worm = interpreter(source, Topology::Torus);
+	worm.step(2).unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (1, 2));
+	assert_eq!(worm.worm().body(), [(1, 0)]);
+	assert_eq!(worm.get((1, 0)), Some(4));
+
+	let mut bounded = interpreter(source, Topology::Bounded);
+	bounded.step(2).unwrap();
+	assert_eq!(bounded.state(), State::EndOfProgram);
+	assert_eq!(bounded.worm().head(), (1, 0));
+}
+
+#[test]
+fn wrap_bottom_edge() {
+	let source = "@v\n  \n  ";
+	let mut worm = interpreter(source, Topology::Torus);
+	worm.step(3).unwrap();
+	assert_eq!(worm.worm().head(), (1, 2));
+	worm.step_once().unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (1, 0));
+
+	let mut bounded = interpreter(source, Topology::Bounded);
+	bounded.step(4).unwrap();
+	assert_eq!(bounded.state(), State::EndOfProgram);
+	assert_eq!(bounded.worm().head(), (1, 2));
+}
+
+#[test]
+fn unbounded_grows_right_and_down() {
+	let mut worm = interpreter("4@\n v", Topology::Unbounded);
+	worm.step_once().unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (2, 0));
+	assert_eq!(worm.origin(), (0, 0));
+	assert!(worm.width() > 2);
+
+	let mut worm = interpreter("@v\n  ", Topology::Unbounded);
+	worm.step(3).unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(worm.worm().head(), (1, 2));
+	assert!(worm.height() > 2);
+}
+
+#[test]
+fn unbounded_grows_up_and_left() {
+	let mut worm = interpreter("@<  ", Topology::Unbounded);
+	worm.step(3).unwrap();
+	assert_eq!(worm.state(), State::Running);
+	let origin = worm.origin();
+	assert!(origin.0 > 0);
+	assert_eq!(origin.1, 0);
+	assert_eq!(worm.worm().head(), (origin.0 - 1, 0));
+	assert_eq!(worm.get((origin.0 + 1, 0)), Some(b'<'));
+
+	let mut worm = interpreter("@^\n 4", Topology::Unbounded);
+	worm.step(2).unwrap();
+	let origin = worm.origin();
+	assert_eq!(origin.0, 0);
+	assert!(origin.1 > 0);
+	assert_eq!(worm.worm().head(), (1, origin.1 - 1));
+	assert_eq!(worm.get((1, origin.1 + 1)), Some(b'4'));
+}
+
+#[test]
+fn wrapping_into_its_own_body_runs_over_it() {
+	let mut worm = interpreter("@12", Topology::Torus);
+	worm.step(3).unwrap();
+	assert_eq!(worm.worm().head(), (0, 0));
+	assert_eq!(worm.worm().body(), [(0, 0), (1, 0), (2, 0)]);
+	assert_eq!(worm.program(), [b"@\x02\x01"]);
+	// segments pile up on the same cells, sharing their values
+	worm.step(3).unwrap();
+	assert_eq!(worm.state(), State::Running);
+	assert_eq!(
+		worm.worm().body(),
+		[(0, 0), (1, 0), (2, 0), (0, 0), (1, 0), (2, 0)]
+	);
+	assert_eq!(worm.worm().values(), [b'@', 1, 2, b'@', 1, 2]);
+}
+
+#[test]
+fn positions_are_counted_from_the_source_after_growing() {
+	let mut worm = interpreter("@^\n 4", Topology::Unbounded);
+	let breakpoint = Breakpoint::Position(1, -1);
+	worm.step(2).unwrap();
+	assert!(worm.origin().1 > 0);
+	assert_eq!(worm.source_position(worm.worm().head()), (1, -1));
+	assert_eq!(worm.last_step().head, (1, -1));
+	assert!(breakpoint.is_hit(&worm));
+	assert_eq!(Expr::parse("y").unwrap().eval(&worm), Ok(Value::Int(-1)));
+	assert!(!Breakpoint::Position(1, 0).is_hit(&worm));
+}
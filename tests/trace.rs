@@ -0,0 +1,71 @@
+use worm::{Direction, SandWormInterpreter, StepInfo, Turn};
+
+fn last_step_after(source: &str, input: &[u8], steps: usize) -> StepInfo {
+	let mut worm = SandWormInterpreter::new(source, input.to_vec()).unwrap();
+	worm.step(steps).unwrap();
+	worm.last_step().clone()
+}
+
+#[test]
+fn records_popped_and_pushed_values() {
+	let step = last_step_after("@12+ ", b"", 3);
+	assert_eq!(step.step, 2);
+	assert_eq!(step.turn, Turn::Moved);
+	assert_eq!(step.instruction, Some(b'+'));
+	assert_eq!(step.popped, [2, 1]);
+	assert_eq!(step.pushed, [3]);
+	assert_eq!(step.head, (3, 0));
+	assert_eq!(step.length, 1);
+}
+
+#[test]
+fn records_input_and_output() {
+	let step = last_step_after("@? ", b"x", 1);
+	assert_eq!(step.input, b"x");
+	assert_eq!(step.pushed, b"x");
+	// reading past the end pushes 0 without reading anything
+	let step = last_step_after("@?? ", b"x", 2);
+	assert!(step.input.is_empty());
+	assert_eq!(step.pushed, [0]);
+
+	// `&` reads the whitespace and digits, but not what comes after
+	let step = last_step_after("@& ", b" 12;", 1);
+	assert_eq!(step.input, b" 12");
+	assert_eq!(step.pushed, [12]);
+
+	let step = last_step_after("@99+\" ", b"", 4);
+	assert_eq!(step.output, b"18");
+	assert_eq!(step.popped, [18]);
+}
+
+#[test]
+fn records_turns_without_moving() {
+	let step = last_step_after("@@ ", b"", 1);
+	assert_eq!(step.worm, 0);
+	assert_eq!(step.turn, Turn::Blocked);
+	assert_eq!(step.instruction, None);
+
+	let step = last_step_after("@", b"", 1);
+	assert_eq!(step.turn, Turn::LeftGrid);
+	assert_eq!(step.head, (0, 0));
+}
+
+#[test]
+fn json_lines() {
+	let step = last_step_after("@1\\\n   ", b"", 2);
+	assert_eq!(step.direction, Direction::Down);
+	assert_eq!(
+		step.to_json(),
+		r#"{"step":1,"worm":0,"turn":"moved","x":2,"y":0,"direction":"down","instruction":"\\","length":0,"popped":[1],"pushed":[],"output":[],"input":[]}"#
+	);
+	let step = StepInfo {
+		instruction: Some(0xe9),
+		..StepInfo::default()
+	};
+	assert!(step.to_json().contains(r#""instruction":"\u00e9""#));
+	let step = StepInfo {
+		instruction: Some(b'"'),
+		..StepInfo::default()
+	};
+	assert!(step.to_json().contains(r#""instruction":"\"""#));
+}
@@ -0,0 +1,64 @@
+use worm::{SandWormInterpreter, State, Topology};
+
+#[test]
+fn every_head_spawns_a_worm() {
+	let worm = SandWormInterpreter::new("@1\n @2", Vec::new()).unwrap();
+	let heads: Vec<_> = worm.worms().iter().map(|w| w.head()).collect();
+	assert_eq!(heads, [(0, 0), (1, 1)]);
+}
+
+#[test]
+fn worms_take_turns() {
+	let mut worm = SandWormInterpreter::new("@1\n@2", Vec::new()).unwrap();
+	worm.step_once().unwrap();
+	assert_eq!(worm.current(), 1);
+	assert_eq!(worm.worms()[0].head(), (1, 0));
+	assert_eq!(worm.worms()[1].head(), (0, 1));
+	worm.step_once().unwrap();
+	assert_eq!(worm.current(), 0);
+	assert_eq!(worm.worms()[1].head(), (1, 1));
+	assert_eq!(worm.worms()[0].body(), [(0, 0)]);
+	assert_eq!(worm.worms()[1].body(), [(0, 1)]);
+	assert_eq!(worm.get((0, 0)), Some(1));
+	assert_eq!(worm.get((0, 1)), Some(2));
+}
+
+#[test]
+fn blocked_worm_waits() {
+	let mut worm = SandWormInterpreter::new("@@ ", Vec::new()).unwrap();
+	worm.step_once().unwrap();
+	assert_eq!(worm.worms()[0].head(), (0, 0));
+	assert_eq!(worm.steps(), 0);
+	worm.step_once().unwrap();
+	assert_eq!(worm.worms()[1].head(), (2, 0));
+	worm.step_once().unwrap();
+	assert_eq!(worm.worms()[0].head(), (1, 0));
+	assert_eq!(worm.steps(), 2);
+}
+
+#[test]
+fn program_ends_when_every_worm_has_left() {
+	let mut worm = SandWormInterpreter::new("@ \n@  ", Vec::new()).unwrap();
+	worm.run().unwrap();
+	assert_eq!(worm.state(), State::EndOfProgram);
+	assert!(worm.worms().iter().all(|w| !w.is_alive()));
+	assert_eq!(worm.steps(), 4);
+}
+
+#[test]
+fn worms_blocking_each_other_deadlock() {
+	let mut worm = SandWormInterpreter::new("@@", Vec::new()).unwrap();
+	worm.set_topology(Topology::Torus);
+	worm.run().unwrap();
+	assert_eq!(worm.state(), State::Deadlocked);
+	assert_eq!(worm.steps(), 0);
+}
+
+#[test]
+fn get_off_the_grid_is_none() {
+	let worm = SandWormInterpreter::new("@1\n2", Vec::new()).unwrap();
+	assert_eq!(worm.get((1, 1)), Some(0));
+	assert_eq!(worm.get((2, 0)), None);
+	assert_eq!(worm.get((0, 2)), None);
+	assert_eq!(worm.get((usize::MAX, usize::MAX)), None);
+}